@@ -1 +1 @@
-rm aes_* rsa_* potpis
+rm aes_* rsa_* potpis digitalna_omotnica otvorena_omotnica digitalni_pecat otvoreni_pecat
@@ -0,0 +1,561 @@
+use std::{cmp::Ordering, fmt, net::IpAddr, os::raw::c_long};
+
+use anyhow::{anyhow, Result};
+use foreign_types::ForeignTypeRef;
+use openssl::{
+    asn1::{Asn1Time, Asn1TimeRef},
+    bn::{BigNum, BigNumRef, MsbOption},
+    hash::MessageDigest,
+    pkey::{HasPublic, PKey, Private},
+    stack::Stack,
+    x509::{
+        extension::{
+            AuthorityKeyIdentifier, BasicConstraints, ExtendedKeyUsage as ExtendedKeyUsageExt,
+            KeyUsage as KeyUsageExt, SubjectKeyIdentifier,
+        },
+        X509Builder, X509Extension, X509Name, X509NameRef, X509Ref, X509Req, X509ReqBuilder, X509,
+    },
+};
+use openssl_sys as ffi;
+
+use crate::{
+    crl::{describe_crl, load_crl},
+    fingerprint::{key_fingerprint, key_title, short_key_id},
+    signing::KeyAlgorithm,
+};
+
+pub const VALIDITY_DAYS: [u32; 5] = [30, 90, 365, 730, 1825];
+const SERIAL_NUMBER_BITS: i32 = 127;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyUsage {
+    DigitalSignature,
+    NonRepudiation,
+    KeyEncipherment,
+    DataEncipherment,
+    KeyAgreement,
+}
+
+impl KeyUsage {
+    pub const ALL: [KeyUsage; 5] = [
+        KeyUsage::DigitalSignature,
+        KeyUsage::NonRepudiation,
+        KeyUsage::KeyEncipherment,
+        KeyUsage::DataEncipherment,
+        KeyUsage::KeyAgreement,
+    ];
+}
+
+impl KeyUsage {
+    fn bit(&self) -> u32 {
+        match self {
+            KeyUsage::DigitalSignature => ffi::X509v3_KU_DIGITAL_SIGNATURE,
+            KeyUsage::NonRepudiation => ffi::X509v3_KU_NON_REPUDIATION,
+            KeyUsage::KeyEncipherment => ffi::X509v3_KU_KEY_ENCIPHERMENT,
+            KeyUsage::DataEncipherment => ffi::X509v3_KU_DATA_ENCIPHERMENT,
+            KeyUsage::KeyAgreement => ffi::X509v3_KU_KEY_AGREEMENT,
+        }
+    }
+}
+
+impl fmt::Display for KeyUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                KeyUsage::DigitalSignature => "Digitalni potpis",
+                KeyUsage::NonRepudiation => "Neporecivost",
+                KeyUsage::KeyEncipherment => "Enkripcija kljuca",
+                KeyUsage::DataEncipherment => "Enkripcija podataka",
+                KeyUsage::KeyAgreement => "Dogovor kljuca",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtendedKeyUsage {
+    ServerAuth,
+    ClientAuth,
+    CodeSigning,
+    EmailProtection,
+    TimeStamping,
+}
+
+impl ExtendedKeyUsage {
+    pub const ALL: [ExtendedKeyUsage; 5] = [
+        ExtendedKeyUsage::ServerAuth,
+        ExtendedKeyUsage::ClientAuth,
+        ExtendedKeyUsage::CodeSigning,
+        ExtendedKeyUsage::EmailProtection,
+        ExtendedKeyUsage::TimeStamping,
+    ];
+}
+
+impl ExtendedKeyUsage {
+    fn bit(&self) -> u32 {
+        match self {
+            ExtendedKeyUsage::ServerAuth => ffi::XKU_SSL_SERVER,
+            ExtendedKeyUsage::ClientAuth => ffi::XKU_SSL_CLIENT,
+            ExtendedKeyUsage::CodeSigning => ffi::XKU_CODE_SIGN,
+            ExtendedKeyUsage::EmailProtection => ffi::XKU_SMIME,
+            ExtendedKeyUsage::TimeStamping => ffi::XKU_TIMESTAMP,
+        }
+    }
+}
+
+impl fmt::Display for ExtendedKeyUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ExtendedKeyUsage::ServerAuth => "Autentikacija posluzitelja",
+                ExtendedKeyUsage::ClientAuth => "Autentikacija klijenta",
+                ExtendedKeyUsage::CodeSigning => "Potpisivanje koda",
+                ExtendedKeyUsage::EmailProtection => "Zastita e-poste",
+                ExtendedKeyUsage::TimeStamping => "Vremenski zigovi",
+            }
+        )
+    }
+}
+
+// Subjekt se zadaje kao "CN=Ime, O=Organizacija, C=HR"
+#[derive(Debug, Clone)]
+pub struct CertificateParams {
+    pub subject: String,
+    pub validity_days: u32,
+    pub key_usage: Vec<KeyUsage>,
+    pub extended_key_usage: Vec<ExtendedKeyUsage>,
+    // Certifikat certifikacijskog tijela smije potpisivati certifikate i CRL-ove
+    pub certificate_authority: bool,
+    pub path_length: Option<u32>,
+}
+
+impl Default for CertificateParams {
+    fn default() -> Self {
+        Self {
+            subject: "CN=os2_projekt".to_string(),
+            validity_days: 365,
+            key_usage: vec![KeyUsage::DigitalSignature],
+            extended_key_usage: Vec::new(),
+            certificate_authority: false,
+            path_length: None,
+        }
+    }
+}
+
+pub fn subject_name(subject: &str) -> Result<X509Name> {
+    let mut builder = X509Name::builder()?;
+    let mut has_common_name = false;
+    for entry in subject.split(',').filter(|entry| !entry.trim().is_empty()) {
+        let (field, value) = entry
+            .split_once('=')
+            .map(|(field, value)| (field.trim(), value.trim()))
+            .ok_or_else(|| anyhow!("Neispravan zapis subjekta: {}", entry.trim()))?;
+        if value.is_empty() {
+            return Err(anyhow!("Polje {} subjekta je prazno", field));
+        }
+        builder
+            .append_entry_by_text(field, value)
+            .map_err(|_| anyhow!("Neispravno polje subjekta: {}", field))?;
+        has_common_name |= field.eq_ignore_ascii_case("CN");
+    }
+    if !has_common_name {
+        return Err(anyhow!("Subjekt mora sadrzavati ime (CN)"));
+    }
+    Ok(builder.build())
+}
+
+pub fn name_to_string(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let field = entry.object().nid().short_name().unwrap_or("?");
+            let value = entry
+                .data()
+                .as_utf8()
+                .map(|value| value.to_string())
+                .unwrap_or_default();
+            format!("{}={}", field, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Ed25519 potpisuje cijelu poruku, bez zasebnog sazetka
+pub fn signature_digest<T: HasPublic>(pkey: &PKey<T>) -> Result<MessageDigest> {
+    Ok(match KeyAlgorithm::of(pkey)? {
+        KeyAlgorithm::Ed25519 => MessageDigest::null(),
+        KeyAlgorithm::EcdsaP384 => MessageDigest::sha384(),
+        KeyAlgorithm::Rsa | KeyAlgorithm::EcdsaP256 => MessageDigest::sha256(),
+    })
+}
+
+pub fn random_serial_number() -> Result<BigNum> {
+    let mut serial = BigNum::new()?;
+    serial.rand(SERIAL_NUMBER_BITS, MsbOption::MAYBE_ZERO, false)?;
+    Ok(serial)
+}
+
+// Sekunde od 1.1.1970. UTC
+pub fn unix_time(time: &Asn1TimeRef) -> Result<u64> {
+    let diff = Asn1Time::from_unix(0)?.diff(time)?;
+    Ok((i64::from(diff.days) * 86400 + i64::from(diff.secs)).try_into()?)
+}
+
+fn key_usage_extensions(params: &CertificateParams) -> Result<Vec<X509Extension>> {
+    let mut extensions = Vec::new();
+    if !params.key_usage.is_empty() || params.certificate_authority {
+        let mut key_usage = KeyUsageExt::new();
+        key_usage.critical();
+        if params.certificate_authority {
+            key_usage.key_cert_sign().crl_sign();
+        }
+        for usage in &params.key_usage {
+            match usage {
+                KeyUsage::DigitalSignature => key_usage.digital_signature(),
+                KeyUsage::NonRepudiation => key_usage.non_repudiation(),
+                KeyUsage::KeyEncipherment => key_usage.key_encipherment(),
+                KeyUsage::DataEncipherment => key_usage.data_encipherment(),
+                KeyUsage::KeyAgreement => key_usage.key_agreement(),
+            };
+        }
+        extensions.push(key_usage.build()?);
+    }
+    if !params.extended_key_usage.is_empty() {
+        let mut extended_key_usage = ExtendedKeyUsageExt::new();
+        for usage in &params.extended_key_usage {
+            match usage {
+                ExtendedKeyUsage::ServerAuth => extended_key_usage.server_auth(),
+                ExtendedKeyUsage::ClientAuth => extended_key_usage.client_auth(),
+                ExtendedKeyUsage::CodeSigning => extended_key_usage.code_signing(),
+                ExtendedKeyUsage::EmailProtection => extended_key_usage.email_protection(),
+                ExtendedKeyUsage::TimeStamping => extended_key_usage.time_stamping(),
+            };
+        }
+        extensions.push(extended_key_usage.build()?);
+    }
+    Ok(extensions)
+}
+
+fn basic_constraints(params: &CertificateParams) -> Result<X509Extension> {
+    let mut basic_constraints = BasicConstraints::new();
+    basic_constraints.critical();
+    if params.certificate_authority {
+        basic_constraints.ca();
+        if let Some(path_length) = params.path_length {
+            basic_constraints.pathlen(path_length);
+        }
+    }
+    Ok(basic_constraints.build()?)
+}
+
+// Bez izdavatelja certifikat je samopotpisan pa signing_key mora odgovarati public_key
+pub fn issue_certificate<T: HasPublic>(
+    subject: &X509NameRef,
+    public_key: &PKey<T>,
+    serial_number: &BigNumRef,
+    params: &CertificateParams,
+    issuer: Option<&X509Ref>,
+    signing_key: &PKey<Private>,
+) -> Result<X509> {
+    let mut builder = X509Builder::new()?;
+    builder.set_version(2)?;
+    let serial_number = serial_number.to_asn1_integer()?;
+    builder.set_serial_number(&serial_number)?;
+    builder.set_subject_name(subject)?;
+    builder.set_issuer_name(issuer.map_or(subject, |issuer| issuer.subject_name()))?;
+    let not_before = Asn1Time::days_from_now(0)?;
+    builder.set_not_before(&not_before)?;
+    let not_after = Asn1Time::days_from_now(params.validity_days)?;
+    builder.set_not_after(&not_after)?;
+    builder.set_pubkey(public_key)?;
+
+    builder.append_extension(basic_constraints(params)?)?;
+    for extension in key_usage_extensions(params)? {
+        builder.append_extension(extension)?;
+    }
+    let subject_key_id =
+        SubjectKeyIdentifier::new().build(&builder.x509v3_context(issuer, None))?;
+    builder.append_extension(subject_key_id)?;
+    let authority_key_id = AuthorityKeyIdentifier::new()
+        .keyid(true)
+        .build(&builder.x509v3_context(issuer, None))?;
+    builder.append_extension(authority_key_id)?;
+
+    builder.sign(signing_key, signature_digest(signing_key)?)?;
+    Ok(builder.build())
+}
+
+pub fn create_self_signed_certificate(
+    pkey: &PKey<Private>,
+    params: &CertificateParams,
+) -> Result<X509> {
+    let subject = subject_name(&params.subject)?;
+    let serial_number = random_serial_number()?;
+    issue_certificate(&subject, pkey, &serial_number, params, None, pkey)
+}
+
+// Zahtjev (PKCS#10) sadrzi subjekt, javni kljuc i trazene namjene kljuca
+pub fn create_certificate_request(
+    pkey: &PKey<Private>,
+    params: &CertificateParams,
+) -> Result<X509Req> {
+    let mut builder = X509ReqBuilder::new()?;
+    builder.set_version(0)?;
+    let name = subject_name(&params.subject)?;
+    builder.set_subject_name(&name)?;
+    builder.set_pubkey(pkey)?;
+    let mut extensions = Stack::new()?;
+    for extension in key_usage_extensions(params)? {
+        extensions.push(extension)?;
+    }
+    if !extensions.is_empty() {
+        builder.add_extensions(&extensions)?;
+    }
+    builder.sign(pkey, signature_digest(pkey)?)?;
+    Ok(builder.build())
+}
+
+pub fn load_certificate(data: &[u8]) -> Result<X509> {
+    X509::from_pem(data)
+        .or_else(|_| X509::from_der(data))
+        .map_err(|_| anyhow!("Datoteka ne sadrzi X.509 certifikat"))
+}
+
+// Lanac u PEM zapisu (vise certifikata redom) ili jedan certifikat u DER zapisu
+pub fn load_certificate_chain(data: &[u8]) -> Result<Vec<X509>> {
+    match X509::stack_from_pem(data) {
+        Ok(chain) if !chain.is_empty() => Ok(chain),
+        _ => Ok(vec![load_certificate(data)?]),
+    }
+}
+
+// openssl-sys ne deklarira X509_get_pathlen (OpenSSL 1.1.0), -1 znaci da ogranicenje nije zadano
+extern "C" {
+    fn X509_get_pathlen(x: *mut ffi::X509) -> c_long;
+}
+
+// rust-openssl nema pristup namjeni kljuca ni osnovnim ogranicenjima certifikata.
+// Bez prosirenja namjene kljuca OpenSSL vraca sve bitove postavljene.
+pub fn allows_signing(cert: &X509Ref) -> bool {
+    let key_usage = unsafe { ffi::X509_get_key_usage(cert.as_ptr()) };
+    key_usage & (ffi::X509v3_KU_DIGITAL_SIGNATURE | ffi::X509v3_KU_NON_REPUDIATION) != 0
+}
+
+pub fn is_certificate_authority(cert: &X509Ref) -> bool {
+    let flags = unsafe { ffi::X509_get_extension_flags(cert.as_ptr()) };
+    flags & ffi::EXFLAG_CA != 0
+}
+
+pub fn load_certificate_request(data: &[u8]) -> Result<X509Req> {
+    X509Req::from_pem(data)
+        .or_else(|_| X509Req::from_der(data))
+        .map_err(|_| anyhow!("Datoteka ne sadrzi zahtjev za certifikat"))
+}
+
+fn describe_public_key<T: HasPublic>(pkey: &PKey<T>) -> Result<String> {
+    Ok(format!(
+        "{}, ID {}",
+        key_title(pkey)?,
+        short_key_id(&key_fingerprint(pkey)?)
+    ))
+}
+
+// Namjene kljuca i osnovna ogranicenja OpenSSL cita iz prosirenja kod prvog pristupa
+fn describe_extensions(cert: &X509Ref) -> Vec<String> {
+    let flags = unsafe { ffi::X509_get_extension_flags(cert.as_ptr()) };
+    let mut lines = Vec::new();
+    if flags & ffi::EXFLAG_BCONS != 0 {
+        let path_length = unsafe { X509_get_pathlen(cert.as_ptr()) };
+        lines.push(match (is_certificate_authority(cert), path_length) {
+            (true, -1) => "  Osnovna ogranicenja: certifikacijsko tijelo".to_string(),
+            (true, path_length) => format!(
+                "  Osnovna ogranicenja: certifikacijsko tijelo (najvise {} podredenih tijela)",
+                path_length
+            ),
+            (false, _) => "  Osnovna ogranicenja: krajnji korisnik".to_string(),
+        });
+    }
+    if flags & ffi::EXFLAG_KUSAGE != 0 {
+        let key_usage = unsafe { ffi::X509_get_key_usage(cert.as_ptr()) };
+        let mut usages: Vec<String> = KeyUsage::ALL
+            .iter()
+            .filter(|usage| key_usage & usage.bit() != 0)
+            .map(ToString::to_string)
+            .collect();
+        if key_usage & ffi::X509v3_KU_KEY_CERT_SIGN != 0 {
+            usages.push("Potpisivanje certifikata".to_string());
+        }
+        if key_usage & ffi::X509v3_KU_CRL_SIGN != 0 {
+            usages.push("Potpisivanje CRL-a".to_string());
+        }
+        lines.push(format!("  Namjena kljuca: {}", usages.join(", ")));
+    }
+    if flags & ffi::EXFLAG_XKUSAGE != 0 {
+        let extended_key_usage = unsafe { ffi::X509_get_extended_key_usage(cert.as_ptr()) };
+        let usages: Vec<String> = ExtendedKeyUsage::ALL
+            .iter()
+            .filter(|usage| extended_key_usage & usage.bit() != 0)
+            .map(ToString::to_string)
+            .collect();
+        lines.push(format!("  Prosirena namjena kljuca: {}", usages.join(", ")));
+    }
+    if let Some(names) = cert.subject_alt_names() {
+        let names: Vec<String> = names
+            .iter()
+            .filter_map(|name| {
+                name.dnsname()
+                    .map(|dns| format!("DNS:{}", dns))
+                    .or_else(|| name.email().map(|email| format!("e-posta:{}", email)))
+                    .or_else(|| name.uri().map(|uri| format!("URI:{}", uri)))
+                    .or_else(|| name.ipaddress().map(format_ip_address))
+            })
+            .collect();
+        lines.push(format!(
+            "  Alternativna imena subjekta: {}",
+            names.join(", ")
+        ));
+    }
+    lines
+}
+
+fn format_ip_address(address: &[u8]) -> String {
+    let address = match <[u8; 4]>::try_from(address) {
+        Ok(v4) => IpAddr::from(v4),
+        Err(_) => match <[u8; 16]>::try_from(address) {
+            Ok(v6) => IpAddr::from(v6),
+            Err(_) => return "IP:?".to_string(),
+        },
+    };
+    format!("IP:{}", address)
+}
+
+// Zahtjev nema OpenSSL pristup namjenama kljuca, pa se trazena prosirenja
+// prenose u nepotpisani certifikat i citaju jednako kao prosirenja certifikata
+fn requested_extensions(request: &X509Req) -> Result<Vec<String>> {
+    let extensions = match request.extensions() {
+        Ok(extensions) => extensions,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut builder = X509Builder::new()?;
+    for extension in &extensions {
+        builder.append_extension2(extension)?;
+    }
+    Ok(describe_extensions(&builder.build()))
+}
+
+pub fn describe_certificate(data: &[u8]) -> Result<String> {
+    if let Ok(cert) = load_certificate(data) {
+        let public_key = cert.public_key()?;
+        // X509_check_issued bi trazio namjenu keyCertSign, pa se usporeduju nazivi
+        let self_signed = cert.issuer_name().try_cmp(cert.subject_name())? == Ordering::Equal;
+        let mut lines = vec![
+            format!("Certifikat (X.509 v{})", cert.version() + 1),
+            format!("Subjekt: {}", name_to_string(cert.subject_name())),
+            format!(
+                "Izdavatelj: {}{}",
+                name_to_string(cert.issuer_name()),
+                if self_signed { " (samopotpisani)" } else { "" }
+            ),
+            format!(
+                "Serijski broj: {}",
+                cert.serial_number().to_bn()?.to_hex_str()?
+            ),
+            format!("Vrijedi od: {}", cert.not_before()),
+            format!("Vrijedi do: {}", cert.not_after()),
+            format!("Javni kljuc: {}", describe_public_key(&public_key)?),
+            format!("Algoritam potpisa: {}", cert.signature_algorithm().object()),
+        ];
+        if self_signed && !cert.verify(&public_key)? {
+            lines.push("Potpis certifikata nije ispravan".to_string());
+        }
+        let extensions = describe_extensions(&cert);
+        if !extensions.is_empty() {
+            lines.push("Prosirenja:".to_string());
+            lines.extend(extensions);
+        }
+        return Ok(lines.join("\n"));
+    }
+
+    if let Ok(crl) = load_crl(data) {
+        return describe_crl(&crl);
+    }
+
+    let request = load_certificate_request(data).map_err(|_| {
+        anyhow!(
+            "Datoteka ne sadrzi certifikat, zahtjev za certifikat ni listu opozvanih certifikata"
+        )
+    })?;
+    let public_key = request.public_key()?;
+    let mut lines = vec![
+        "Zahtjev za certifikat (PKCS#10)".to_string(),
+        format!("Subjekt: {}", name_to_string(request.subject_name())),
+        format!("Javni kljuc: {}", describe_public_key(&public_key)?),
+        if request.verify(&public_key)? {
+            "Potpis zahtjeva je ispravan".to_string()
+        } else {
+            "Potpis zahtjeva nije ispravan".to_string()
+        },
+    ];
+    let extensions = requested_extensions(&request)?;
+    if !extensions.is_empty() {
+        lines.push("Trazena prosirenja:".to_string());
+        lines.extend(extensions);
+    }
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::{ec::EcGroup, ec::EcKey, nid::Nid, rsa::Rsa};
+
+    #[test]
+    fn certificates_and_requests_for_every_key_type() {
+        let p384 = EcGroup::from_curve_name(Nid::SECP384R1).unwrap();
+        let keys = [
+            PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap(),
+            PKey::generate_ed25519().unwrap(),
+            PKey::from_ec_key(EcKey::generate(&p384).unwrap()).unwrap(),
+        ];
+        let params = CertificateParams {
+            subject: "CN=Ivan Horvat, O=FER, C=HR".to_string(),
+            validity_days: 30,
+            key_usage: vec![KeyUsage::DigitalSignature, KeyUsage::NonRepudiation],
+            extended_key_usage: vec![ExtendedKeyUsage::EmailProtection],
+            ..CertificateParams::default()
+        };
+        for pkey in keys {
+            let cert = create_self_signed_certificate(&pkey, &params).unwrap();
+            assert!(cert.verify(&pkey).unwrap());
+            assert!(cert.public_key().unwrap().public_eq(&pkey));
+            assert_eq!(
+                name_to_string(cert.subject_name()),
+                "CN=Ivan Horvat, O=FER, C=HR"
+            );
+            let expires = Asn1Time::days_from_now(30).unwrap();
+            assert_eq!(cert.not_after().diff(&expires).unwrap().days, 0);
+
+            let description = describe_certificate(&cert.to_pem().unwrap()).unwrap();
+            assert!(description.contains("(samopotpisani)"));
+            assert!(description.contains("Namjena kljuca: Digitalni potpis, Neporecivost\n"));
+            assert!(description.contains("Prosirena namjena kljuca: Zastita e-poste"));
+            assert!(description.contains("Osnovna ogranicenja: krajnji korisnik"));
+            assert!(describe_certificate(&cert.to_der().unwrap()).is_ok());
+
+            let request = create_certificate_request(&pkey, &params).unwrap();
+            assert!(request.verify(&pkey).unwrap());
+            let description = describe_certificate(&request.to_pem().unwrap()).unwrap();
+            assert!(description.contains("Potpis zahtjeva je ispravan"));
+            assert!(description.contains("Namjena kljuca: Digitalni potpis, Neporecivost\n"));
+            assert!(description.contains("Prosirena namjena kljuca: Zastita e-poste"));
+        }
+
+        assert!(subject_name("O=FER").is_err());
+        assert!(subject_name("CN=Ivan, XX=1").is_err());
+        assert!(subject_name("CN").is_err());
+        assert!(describe_certificate(b"nije certifikat").is_err());
+    }
+}
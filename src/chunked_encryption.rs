@@ -0,0 +1,576 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+use anyhow::{anyhow, Result};
+use openssl::symm::{decrypt_aead, encrypt_aead};
+
+use crate::{
+    encryption::{AeadAlgorithm, ShaHash, AEAD_TAG_LENGTH, FINGERPRINT_LENGTH},
+    file_manip::{write_file_streamed, STREAM_BUFFER_SIZE},
+    fingerprint::short_key_id,
+    keygen::Keygen,
+};
+
+const CHUNKED_MAGIC: &[u8; 4] = b"OS2S";
+const CHUNKED_VERSION: u8 = 1;
+const NONCE_PREFIX_LENGTH: usize = 7;
+const HEADER_LENGTH: usize = CHUNKED_MAGIC.len() + 2 + 4 + NONCE_PREFIX_LENGTH + FINGERPRINT_LENGTH;
+const MAX_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+// Kod greske se trazi je li dio premjesten, ali samo medu susjednim dijelovima
+const REORDER_SEARCH_WINDOW: u64 = 256;
+// Ukupno se pri trazenju pokusava dekriptirati najvise ovoliko bajtova,
+// pa se kod velikih dijelova premjestanje ne trazi
+const REORDER_SEARCH_BYTES: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum ChunkedError {
+    Malformed,
+    WrongKey,
+    Truncated { chunks: u64 },
+    TrailingData { chunk: u64 },
+    Reordered { position: u64, chunk: u64 },
+    Corrupted { chunk: u64 },
+    OutOfRange { chunk: u64, chunks: u64 },
+}
+
+impl fmt::Display for ChunkedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkedError::Malformed => {
+                write!(f, "Neispravan format datoteke kriptirane u dijelovima")
+            }
+            ChunkedError::WrongKey => write!(f, "Datoteka je kriptirana drugim tajnim kljucem"),
+            ChunkedError::Truncated { chunks } => write!(
+                f,
+                "Datoteka je skracena: nakon {} dijelova nedostaje zavrsni dio",
+                chunks
+            ),
+            ChunkedError::TrailingData { chunk } => write!(
+                f,
+                "Dio {} je zavrsni dio, a iza njega postoje dodatni podaci",
+                chunk
+            ),
+            ChunkedError::Reordered { position, chunk } => write!(
+                f,
+                "Promijenjen redoslijed: na mjestu dijela {} nalazi se dio {}",
+                position, chunk
+            ),
+            ChunkedError::Corrupted { chunk } => {
+                write!(f, "Dio {} je izmijenjen ili ostecen", chunk)
+            }
+            ChunkedError::OutOfRange { chunk, chunks } => write!(
+                f,
+                "Dio {} ne postoji (datoteka ima {} dijelova)",
+                chunk, chunks
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ChunkedError {}
+
+// [magic][verzija][algoritam][velicina dijela u32 BE][prefiks noncea][otisak kljuca]
+// Zaglavlje je dodatni autentificirani podatak (AAD) svakog dijela.
+pub struct ChunkedHeader {
+    pub algorithm: AeadAlgorithm,
+    pub chunk_size: u32,
+    nonce_prefix: [u8; NONCE_PREFIX_LENGTH],
+    key_fingerprint: Vec<u8>,
+}
+
+impl ChunkedHeader {
+    pub fn is_chunked(data: &[u8]) -> bool {
+        data.starts_with(CHUNKED_MAGIC)
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = CHUNKED_MAGIC.to_vec();
+        out.push(CHUNKED_VERSION);
+        out.push(self.algorithm.id());
+        out.extend_from_slice(&self.chunk_size.to_be_bytes());
+        out.extend_from_slice(&self.nonce_prefix);
+        out.extend_from_slice(&self.key_fingerprint);
+        out
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut header = [0; HEADER_LENGTH];
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| ChunkedError::Malformed)?;
+        if !ChunkedHeader::is_chunked(&header) {
+            return Err(ChunkedError::Malformed.into());
+        }
+        let version = header[CHUNKED_MAGIC.len()];
+        if version != CHUNKED_VERSION {
+            return Err(anyhow!(
+                "Nepodrzana verzija datoteke kriptirane u dijelovima ({})",
+                version
+            ));
+        }
+        let rest = &header[CHUNKED_MAGIC.len() + 1..];
+        let algorithm = AeadAlgorithm::from_id(rest[0])?;
+        let chunk_size = u32::from_be_bytes(rest[1..5].try_into()?);
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(ChunkedError::Malformed.into());
+        }
+        let nonce_prefix = rest[5..5 + NONCE_PREFIX_LENGTH].try_into()?;
+        Ok(Self {
+            algorithm,
+            chunk_size,
+            nonce_prefix,
+            key_fingerprint: rest[5 + NONCE_PREFIX_LENGTH..].to_vec(),
+        })
+    }
+
+    fn encrypted_chunk_size(&self) -> u64 {
+        self.chunk_size as u64 + AEAD_TAG_LENGTH as u64
+    }
+}
+
+impl fmt::Display for ChunkedHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Kriptirano u dijelovima: {}, velicina dijela {} B, ID kljuca {}",
+            self.algorithm,
+            self.chunk_size,
+            short_key_id(&self.key_fingerprint)
+        )
+    }
+}
+
+pub fn is_chunked_file(filename: &str) -> bool {
+    let mut magic = Vec::with_capacity(CHUNKED_MAGIC.len());
+    match File::open(filename) {
+        Ok(file) => file
+            .take(CHUNKED_MAGIC.len() as u64)
+            .read_to_end(&mut magic)
+            .is_ok_and(|_| ChunkedHeader::is_chunked(&magic)),
+        Err(_) => false,
+    }
+}
+
+pub fn check_chunk_size(chunk_size: u32) -> Result<()> {
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+        return Err(anyhow!(
+            "Velicina dijela mora biti izmedu 1 i {} B",
+            MAX_CHUNK_SIZE
+        ));
+    }
+    Ok(())
+}
+
+// Svaki dio se autentificira zasebno (STREAM konstrukcija):
+// nonce = [prefiks noncea][redni broj dijela u32 BE][1 ako je zavrsni dio, inace 0]
+// pa se premjestanje dijelova i skracivanje datoteke otkrivaju kod dekripcije.
+pub struct ChunkedAead {
+    algorithm: AeadAlgorithm,
+    key: Vec<u8>,
+    chunk_size: u32,
+}
+
+impl ChunkedAead {
+    pub fn new(algorithm: AeadAlgorithm, key: Vec<u8>) -> Self {
+        Self {
+            algorithm,
+            key,
+            chunk_size: STREAM_BUFFER_SIZE as u32,
+        }
+    }
+
+    pub fn set_chunk_size(&mut self, chunk_size: u32) -> Result<()> {
+        check_chunk_size(chunk_size)?;
+        self.chunk_size = chunk_size;
+        Ok(())
+    }
+
+    pub fn encrypt_stream<R: Read, W: Write>(&self, reader: &mut R, writer: &mut W) -> Result<()> {
+        let header = ChunkedHeader {
+            algorithm: self.algorithm,
+            chunk_size: self.chunk_size,
+            nonce_prefix: Keygen::default().generate_56bit_nonce_prefix(),
+            key_fingerprint: ShaHash::hash(&self.key)?,
+        };
+        let aad = header.to_bytes();
+        writer.write_all(&aad)?;
+
+        // Dio je zavrsni tek kad se zna da iza njega nema podataka
+        let chunk_size = self.chunk_size as usize;
+        let mut chunk = read_full(reader, chunk_size)?;
+        let mut index = 0;
+        loop {
+            let next = if chunk.len() < chunk_size {
+                Vec::new()
+            } else {
+                read_full(reader, chunk_size)?
+            };
+            let last = next.is_empty();
+            let mut tag = [0; AEAD_TAG_LENGTH];
+            let encrypted = encrypt_aead(
+                self.algorithm.cipher(),
+                &self.key,
+                Some(&chunk_nonce(&header, index, last)?),
+                &aad,
+                &chunk,
+                &mut tag,
+            )?;
+            writer.write_all(&encrypted)?;
+            writer.write_all(&tag)?;
+            if last {
+                return Ok(());
+            }
+            chunk = next;
+            index += 1;
+        }
+    }
+
+    pub fn decrypt_stream<R: Read, W: Write>(&self, reader: &mut R, writer: &mut W) -> Result<()> {
+        let header = self.read_header(reader)?;
+        let aad = header.to_bytes();
+        let encrypted_chunk_size = header.encrypted_chunk_size() as usize;
+
+        let mut chunk = read_full(reader, encrypted_chunk_size)?;
+        if chunk.is_empty() {
+            return Err(ChunkedError::Truncated { chunks: 0 }.into());
+        }
+        let mut index = 0;
+        loop {
+            let next = if chunk.len() < encrypted_chunk_size {
+                Vec::new()
+            } else {
+                read_full(reader, encrypted_chunk_size)?
+            };
+            let last = next.is_empty();
+            let data = match self.open_chunk(&header, &aad, index, last, &chunk) {
+                Some(data) => data,
+                None => return Err(self.diagnose(&header, &aad, index, last, &chunk).into()),
+            };
+            writer.write_all(&data)?;
+            if last {
+                return Ok(());
+            }
+            chunk = next;
+            index += 1;
+        }
+    }
+
+    // Dekriptira samo dio N bez obrade dijelova ispred njega
+    pub fn decrypt_chunk<R: Read + Seek>(&self, reader: &mut R, index: u64) -> Result<Vec<u8>> {
+        reader.seek(SeekFrom::Start(0))?;
+        let header = self.read_header(reader)?;
+        let aad = header.to_bytes();
+        let chunks = chunk_count(reader, &header)?;
+        if index >= chunks {
+            return Err(ChunkedError::OutOfRange {
+                chunk: index,
+                chunks,
+            }
+            .into());
+        }
+
+        let offset = HEADER_LENGTH as u64 + index * header.encrypted_chunk_size();
+        reader.seek(SeekFrom::Start(offset))?;
+        let chunk = read_full(reader, header.encrypted_chunk_size() as usize)?;
+        let last = index == chunks - 1;
+        match self.open_chunk(&header, &aad, index, last, &chunk) {
+            Some(data) => Ok(data),
+            None => Err(self.diagnose(&header, &aad, index, last, &chunk).into()),
+        }
+    }
+
+    // Raspon otvorenog teksta [offset, offset + length) zapisuje se dio po dio,
+    // a raspon koji izlazi izvan datoteke skracuje se na kraj otvorenog teksta
+    pub fn decrypt_range<R: Read + Seek, W: Write>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+        offset: u64,
+        length: u64,
+    ) -> Result<()> {
+        reader.seek(SeekFrom::Start(0))?;
+        let header = self.read_header(reader)?;
+        let end = offset
+            .checked_add(length)
+            .ok_or_else(|| anyhow!("Raspon izlazi izvan podrzane velicine datoteke"))?
+            .min(plaintext_length(reader, &header)?);
+        if offset >= end {
+            return Ok(());
+        }
+        let chunk_size = header.chunk_size as u64;
+        for index in offset / chunk_size..=(end - 1) / chunk_size {
+            let data = self.decrypt_chunk(reader, index)?;
+            let chunk_start = index * chunk_size;
+            let from = offset.saturating_sub(chunk_start) as usize;
+            let to = (end - chunk_start).min(chunk_size) as usize;
+            writer.write_all(&data[from..to])?;
+        }
+        Ok(())
+    }
+
+    pub fn encrypt_file(&self, filename: &str, output_filename: &str) -> Result<()> {
+        let mut reader = File::open(filename)?;
+        write_file_streamed(output_filename, |writer| {
+            self.encrypt_stream(&mut reader, writer)
+        })
+    }
+
+    pub fn decrypt_file(&self, filename: &str, output_filename: &str) -> Result<()> {
+        let mut reader = File::open(filename)?;
+        write_file_streamed(output_filename, |writer| {
+            self.decrypt_stream(&mut reader, writer)
+        })
+    }
+
+    fn read_header<R: Read>(&self, reader: &mut R) -> Result<ChunkedHeader> {
+        let header = ChunkedHeader::read_from(reader)?;
+        if header.key_fingerprint != ShaHash::hash(&self.key)? {
+            return Err(ChunkedError::WrongKey.into());
+        }
+        Ok(header)
+    }
+
+    fn open_chunk(
+        &self,
+        header: &ChunkedHeader,
+        aad: &[u8],
+        index: u64,
+        last: bool,
+        chunk: &[u8],
+    ) -> Option<Vec<u8>> {
+        if chunk.len() < AEAD_TAG_LENGTH {
+            return None;
+        }
+        let (encrypted, tag) = chunk.split_at(chunk.len() - AEAD_TAG_LENGTH);
+        let nonce = chunk_nonce(header, index, last).ok()?;
+        decrypt_aead(
+            header.algorithm.cipher(),
+            &self.key,
+            Some(&nonce),
+            aad,
+            encrypted,
+            tag,
+        )
+        .ok()
+    }
+
+    // Dio koji se ne moze autentificirati na svom mjestu se pokusava autentificirati
+    // kao zavrsni/nezavrsni ili kao neki od susjednih dijelova
+    fn diagnose(
+        &self,
+        header: &ChunkedHeader,
+        aad: &[u8],
+        position: u64,
+        last: bool,
+        chunk: &[u8],
+    ) -> ChunkedError {
+        if self
+            .open_chunk(header, aad, position, !last, chunk)
+            .is_some()
+        {
+            return if last {
+                ChunkedError::Truncated {
+                    chunks: position + 1,
+                }
+            } else {
+                ChunkedError::TrailingData { chunk: position }
+            };
+        }
+        let window = reorder_search_window(chunk.len());
+        let from = position.saturating_sub(window);
+        for index in (from..position + window).filter(|i| *i != position) {
+            for final_chunk in [false, true] {
+                if self
+                    .open_chunk(header, aad, index, final_chunk, chunk)
+                    .is_some()
+                {
+                    return ChunkedError::Reordered {
+                        position,
+                        chunk: index,
+                    };
+                }
+            }
+        }
+        ChunkedError::Corrupted { chunk: position }
+    }
+}
+
+// Svaki susjedni dio se pokusava dekriptirati kao zavrsni i kao nezavrsni
+fn reorder_search_window(encrypted_chunk_length: usize) -> u64 {
+    let attempt = 4 * encrypted_chunk_length.max(1) as u64;
+    (REORDER_SEARCH_BYTES / attempt).min(REORDER_SEARCH_WINDOW)
+}
+
+fn chunk_nonce(header: &ChunkedHeader, index: u64, last: bool) -> Result<Vec<u8>> {
+    let index = u32::try_from(index).map_err(|_| anyhow!("Datoteka ima previse dijelova"))?;
+    let mut nonce = header.nonce_prefix.to_vec();
+    nonce.extend_from_slice(&index.to_be_bytes());
+    nonce.push(last as u8);
+    Ok(nonce)
+}
+
+// Broj dijelova iz duljine datoteke, zadnji dio moze biti kraci
+fn chunk_count<R: Seek>(reader: &mut R, header: &ChunkedHeader) -> Result<u64> {
+    let length = reader.seek(SeekFrom::End(0))?;
+    let body = length.saturating_sub(HEADER_LENGTH as u64);
+    if body == 0 {
+        return Err(ChunkedError::Truncated { chunks: 0 }.into());
+    }
+    Ok(body.div_ceil(header.encrypted_chunk_size()))
+}
+
+// Svaki dio ima oznaku autentifikacije, pa se duljina otvorenog teksta zna bez dekripcije
+fn plaintext_length<R: Seek>(reader: &mut R, header: &ChunkedHeader) -> Result<u64> {
+    let chunks = chunk_count(reader, header)?;
+    let body = reader.seek(SeekFrom::End(0))? - HEADER_LENGTH as u64;
+    Ok(body.saturating_sub(chunks * AEAD_TAG_LENGTH as u64))
+}
+
+// Cita do `length` bajtova, manje samo na kraju datoteke
+fn read_full<R: Read>(reader: &mut R, length: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(length);
+    reader.take(length as u64).read_to_end(&mut out)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const CHUNK_SIZE: usize = 1000;
+
+    fn decrypt_error(aead: &ChunkedAead, encrypted: &[u8]) -> ChunkedError {
+        let error = aead
+            .decrypt_stream(&mut &encrypted[..], &mut Vec::new())
+            .unwrap_err();
+        error.downcast::<ChunkedError>().unwrap()
+    }
+
+    #[test]
+    fn chunks_are_random_access_and_tampering_is_detected() {
+        let mut aead = ChunkedAead::new(AeadAlgorithm::Aes256Gcm, vec![3; 32]);
+        aead.set_chunk_size(CHUNK_SIZE as u32).unwrap();
+
+        // Zadnji dio je kraci od ostalih
+        let data: Vec<u8> = (0..CHUNK_SIZE * 2 + 500).map(|i| i as u8).collect();
+        let mut encrypted = Vec::new();
+        aead.encrypt_stream(&mut &data[..], &mut encrypted).unwrap();
+        let mut decrypted = Vec::new();
+        aead.decrypt_stream(&mut &encrypted[..], &mut decrypted)
+            .unwrap();
+        assert_eq!(decrypted, data);
+        assert_eq!(
+            aead.decrypt_chunk(&mut Cursor::new(&encrypted), 2).unwrap(),
+            &data[CHUNK_SIZE * 2..]
+        );
+
+        let data: Vec<u8> = (0..CHUNK_SIZE * 10).map(|i| i as u8).collect();
+        let mut encrypted = Vec::new();
+        aead.encrypt_stream(&mut &data[..], &mut encrypted).unwrap();
+        let mut decrypted = Vec::new();
+        aead.decrypt_stream(&mut &encrypted[..], &mut decrypted)
+            .unwrap();
+        assert_eq!(decrypted, data);
+
+        let mut reader = Cursor::new(&encrypted);
+        assert_eq!(
+            aead.decrypt_chunk(&mut reader, 3).unwrap(),
+            &data[CHUNK_SIZE * 3..CHUNK_SIZE * 4]
+        );
+        let mut range = Vec::new();
+        aead.decrypt_range(&mut reader, &mut range, 2500, 3000)
+            .unwrap();
+        assert_eq!(range, &data[2500..5500]);
+        let error = aead.decrypt_chunk(&mut reader, 10).unwrap_err();
+        assert!(matches!(
+            error.downcast::<ChunkedError>().unwrap(),
+            ChunkedError::OutOfRange {
+                chunk: 10,
+                chunks: 10
+            }
+        ));
+
+        let chunk = |index: usize| HEADER_LENGTH + index * (CHUNK_SIZE + AEAD_TAG_LENGTH);
+
+        let truncated = &encrypted[..chunk(9)];
+        assert!(matches!(
+            decrypt_error(&aead, truncated),
+            ChunkedError::Truncated { chunks: 9 }
+        ));
+
+        let mut reordered = encrypted.clone();
+        reordered[chunk(1)..chunk(3)].rotate_left(CHUNK_SIZE + AEAD_TAG_LENGTH);
+        assert!(matches!(
+            decrypt_error(&aead, &reordered),
+            ChunkedError::Reordered {
+                position: 1,
+                chunk: 2
+            }
+        ));
+
+        let mut appended = encrypted.clone();
+        appended.extend_from_slice(&encrypted[chunk(0)..chunk(1)]);
+        assert!(matches!(
+            decrypt_error(&aead, &appended),
+            ChunkedError::TrailingData { chunk: 9 }
+        ));
+
+        let mut corrupted = encrypted.clone();
+        corrupted[chunk(4) + 7] ^= 1;
+        assert!(matches!(
+            decrypt_error(&aead, &corrupted),
+            ChunkedError::Corrupted { chunk: 4 }
+        ));
+    }
+
+    #[test]
+    fn reorder_search_is_bounded_by_total_bytes() {
+        assert_eq!(reorder_search_window(CHUNK_SIZE), REORDER_SEARCH_WINDOW);
+        assert_eq!(
+            reorder_search_window(MAX_CHUNK_SIZE as usize + AEAD_TAG_LENGTH),
+            0
+        );
+        for chunk_size in [1, CHUNK_SIZE, 64 * 1024, 1024 * 1024, 4 * 1024 * 1024] {
+            let encrypted_chunk_size = chunk_size + AEAD_TAG_LENGTH;
+            // Dva pokusaja za svaki dio s obje strane
+            let tried =
+                2 * 2 * reorder_search_window(encrypted_chunk_size) * encrypted_chunk_size as u64;
+            assert!(tried <= REORDER_SEARCH_BYTES, "{}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn range_end_overflow_is_an_error() {
+        let aead = ChunkedAead::new(AeadAlgorithm::Aes256Gcm, vec![3; 32]);
+        let mut encrypted = Vec::new();
+        aead.encrypt_stream(&mut &[1, 2, 3][..], &mut encrypted)
+            .unwrap();
+        assert!(aead
+            .decrypt_range(&mut Cursor::new(&encrypted), &mut Vec::new(), u64::MAX, 2)
+            .is_err());
+    }
+
+    #[test]
+    fn ranges_past_end_are_clamped() {
+        let mut aead = ChunkedAead::new(AeadAlgorithm::ChaCha20Poly1305, vec![5; 32]);
+        aead.set_chunk_size(CHUNK_SIZE as u32).unwrap();
+        let data: Vec<u8> = (0..CHUNK_SIZE * 2 + 500).map(|i| i as u8).collect();
+        let mut encrypted = Vec::new();
+        aead.encrypt_stream(&mut &data[..], &mut encrypted).unwrap();
+
+        let range = |offset: u64, length: u64| {
+            let mut out = Vec::new();
+            aead.decrypt_range(&mut Cursor::new(&encrypted), &mut out, offset, length)
+                .unwrap();
+            out
+        };
+        assert_eq!(range(1800, 10_000), &data[1800..]);
+        assert_eq!(range(0, u64::MAX / 2), data);
+        assert!(range(data.len() as u64, 10).is_empty());
+        assert!(range(100_000, 10).is_empty());
+    }
+}
@@ -0,0 +1,554 @@
+use std::{
+    fmt,
+    os::raw::{c_int, c_long},
+    ptr,
+};
+
+use anyhow::{anyhow, Result};
+use foreign_types::{foreign_type, ForeignType, ForeignTypeRef};
+use openssl::{
+    asn1::{Asn1Time, Asn1TimeRef},
+    base64::{decode_block, encode_block},
+    bn::{BigNum, BigNumRef},
+    error::ErrorStack,
+    pkey::{HasPublic, PKey, Private},
+    x509::{X509NameRef, X509Ref},
+};
+use openssl_sys as ffi;
+
+use crate::{
+    certificate::{name_to_string, signature_digest, unix_time},
+    signing::format_timestamp,
+};
+
+const CRL_PEM_BEGIN: &str = "-----BEGIN X509 CRL-----";
+const CRL_PEM_END: &str = "-----END X509 CRL-----";
+const PEM_LINE_LENGTH: usize = 64;
+// CRL verzije 2, potrebna za prosirenja
+const CRL_VERSION_2: c_long = 1;
+
+// rust-openssl nema podrsku za liste opozvanih certifikata
+foreign_type! {
+    type CType = ffi::X509_CRL;
+    fn drop = ffi::X509_CRL_free;
+    pub struct X509Crl;
+    pub struct X509CrlRef;
+}
+
+// Razlozi opoziva iz RFC 5280 (CRLReason), nepoznati kodovi se cuvaju kakvi jesu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevocationReason {
+    Unspecified,
+    KeyCompromise,
+    CaCompromise,
+    AffiliationChanged,
+    Superseded,
+    CessationOfOperation,
+    CertificateHold,
+    RemoveFromCrl,
+    PrivilegeWithdrawn,
+    AaCompromise,
+    Unknown(u8),
+}
+
+impl RevocationReason {
+    // removeFromCRL se koristi samo u delta listama pa se ne nudi pri opozivu
+    pub const ALL: [RevocationReason; 9] = [
+        RevocationReason::Unspecified,
+        RevocationReason::KeyCompromise,
+        RevocationReason::CaCompromise,
+        RevocationReason::AffiliationChanged,
+        RevocationReason::Superseded,
+        RevocationReason::CessationOfOperation,
+        RevocationReason::CertificateHold,
+        RevocationReason::PrivilegeWithdrawn,
+        RevocationReason::AaCompromise,
+    ];
+
+    fn code(&self) -> u8 {
+        match self {
+            RevocationReason::Unspecified => 0,
+            RevocationReason::KeyCompromise => 1,
+            RevocationReason::CaCompromise => 2,
+            RevocationReason::AffiliationChanged => 3,
+            RevocationReason::Superseded => 4,
+            RevocationReason::CessationOfOperation => 5,
+            RevocationReason::CertificateHold => 6,
+            RevocationReason::RemoveFromCrl => 8,
+            RevocationReason::PrivilegeWithdrawn => 9,
+            RevocationReason::AaCompromise => 10,
+            RevocationReason::Unknown(code) => *code,
+        }
+    }
+
+    fn from_code(code: u8) -> Self {
+        match code {
+            0 => RevocationReason::Unspecified,
+            1 => RevocationReason::KeyCompromise,
+            2 => RevocationReason::CaCompromise,
+            3 => RevocationReason::AffiliationChanged,
+            4 => RevocationReason::Superseded,
+            5 => RevocationReason::CessationOfOperation,
+            6 => RevocationReason::CertificateHold,
+            8 => RevocationReason::RemoveFromCrl,
+            9 => RevocationReason::PrivilegeWithdrawn,
+            10 => RevocationReason::AaCompromise,
+            code => RevocationReason::Unknown(code),
+        }
+    }
+
+    // Nazivi kao u index.txt od `openssl ca`, nepoznati razlog se zapisuje kodom
+    pub fn name(&self) -> String {
+        match self {
+            RevocationReason::Unspecified => "unspecified",
+            RevocationReason::KeyCompromise => "keyCompromise",
+            RevocationReason::CaCompromise => "CACompromise",
+            RevocationReason::AffiliationChanged => "affiliationChanged",
+            RevocationReason::Superseded => "superseded",
+            RevocationReason::CessationOfOperation => "cessationOfOperation",
+            RevocationReason::CertificateHold => "certificateHold",
+            RevocationReason::RemoveFromCrl => "removeFromCRL",
+            RevocationReason::PrivilegeWithdrawn => "privilegeWithdrawn",
+            RevocationReason::AaCompromise => "AACompromise",
+            RevocationReason::Unknown(code) => return code.to_string(),
+        }
+        .to_string()
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .chain([RevocationReason::RemoveFromCrl])
+            .find(|reason| reason.name() == name)
+            .or_else(|| name.parse().ok().map(Self::from_code))
+            .ok_or_else(|| anyhow!("Nepoznat razlog opoziva: {}", name))
+    }
+}
+
+impl fmt::Display for RevocationReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                RevocationReason::Unspecified => "nije naveden",
+                RevocationReason::KeyCompromise => "kompromitiran kljuc",
+                RevocationReason::CaCompromise => "kompromitirano certifikacijsko tijelo",
+                RevocationReason::AffiliationChanged => "promjena pripadnosti",
+                RevocationReason::Superseded => "zamijenjen novim certifikatom",
+                RevocationReason::CessationOfOperation => "prestanak rada",
+                RevocationReason::CertificateHold => "privremeno suspendiran",
+                RevocationReason::RemoveFromCrl => "uklonjen iz liste",
+                RevocationReason::PrivilegeWithdrawn => "povucena ovlastenja",
+                RevocationReason::AaCompromise => "kompromitirano tijelo za atribute",
+                RevocationReason::Unknown(code) => return write!(f, "nepoznat razlog ({})", code),
+            }
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct RevokedCertificate {
+    pub serial_number: BigNum,
+    // Sekunde od 1.1.1970. UTC
+    pub revocation_date: u64,
+    pub reason: RevocationReason,
+}
+
+impl fmt::Display for RevokedCertificate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "opozvan {}, razlog: {}",
+            format_timestamp(self.revocation_date),
+            self.reason
+        )
+    }
+}
+
+impl X509Crl {
+    // Lista se potpisuje kljucem izdavatelja i sadrzi redni broj liste (cRLNumber),
+    // a razlog opoziva se zapisuje samo ako je naveden
+    pub fn create(
+        issuer: &X509Ref,
+        key: &PKey<Private>,
+        revoked: &[RevokedCertificate],
+        crl_number: &BigNumRef,
+        next_update_days: u32,
+    ) -> Result<Self> {
+        let last_update = Asn1Time::days_from_now(0)?;
+        let next_update = Asn1Time::days_from_now(next_update_days)?;
+        let digest = signature_digest(key)?;
+        unsafe {
+            let crl = X509Crl::from_ptr(cvt_p(ffi::X509_CRL_new())?);
+            cvt(ffi::X509_CRL_set_version(crl.as_ptr(), CRL_VERSION_2))?;
+            cvt(ffi::X509_CRL_set_issuer_name(
+                crl.as_ptr(),
+                issuer.subject_name().as_ptr(),
+            ))?;
+            cvt(ffi::X509_CRL_set1_lastUpdate(
+                crl.as_ptr(),
+                last_update.as_ptr(),
+            ))?;
+            cvt(ffi::X509_CRL_set1_nextUpdate(
+                crl.as_ptr(),
+                next_update.as_ptr(),
+            ))?;
+            for entry in revoked {
+                let revoked = cvt_p(ffi::X509_REVOKED_new())?;
+                let added = set_revoked(revoked, entry)
+                    .and_then(|_| Ok(cvt(ffi::X509_CRL_add0_revoked(crl.as_ptr(), revoked))?));
+                if let Err(e) = added {
+                    ffi::X509_REVOKED_free(revoked);
+                    return Err(e);
+                }
+            }
+            cvt(ffi::X509_CRL_sort(crl.as_ptr()))?;
+
+            let number = cvt_p(ffi::BN_to_ASN1_INTEGER(
+                crl_number.as_ptr(),
+                ptr::null_mut(),
+            ))?;
+            let added = ffi::X509_CRL_add1_ext_i2d(
+                crl.as_ptr(),
+                ffi::NID_crl_number,
+                number as *mut _,
+                0,
+                0,
+            );
+            ffi::ASN1_INTEGER_free(number);
+            cvt(added)?;
+            cvt(ffi::X509_CRL_sign(
+                crl.as_ptr(),
+                key.as_ptr(),
+                digest.as_ptr(),
+            ))?;
+            Ok(crl)
+        }
+    }
+
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        unsafe {
+            let mut data = der.as_ptr();
+            let crl = ffi::d2i_X509_CRL(ptr::null_mut(), &mut data, der.len() as c_long);
+            Ok(X509Crl::from_ptr(cvt_p(crl)?))
+        }
+    }
+}
+
+impl X509CrlRef {
+    pub fn to_der(&self) -> Result<Vec<u8>> {
+        unsafe {
+            let length = cvt(ffi::i2d_X509_CRL(self.as_ptr(), ptr::null_mut()))?;
+            let mut der = vec![0; length as usize];
+            cvt(ffi::i2d_X509_CRL(self.as_ptr(), &mut der.as_mut_ptr()))?;
+            Ok(der)
+        }
+    }
+
+    pub fn to_pem(&self) -> Result<Vec<u8>> {
+        let encoded = encode_block(&self.to_der()?);
+        let mut pem = format!("{}\n", CRL_PEM_BEGIN);
+        for line in encoded.as_bytes().chunks(PEM_LINE_LENGTH) {
+            pem.push_str(&String::from_utf8_lossy(line));
+            pem.push('\n');
+        }
+        pem.push_str(CRL_PEM_END);
+        pem.push('\n');
+        Ok(pem.into_bytes())
+    }
+
+    pub fn issuer_name(&self) -> &X509NameRef {
+        unsafe { X509NameRef::from_ptr(ffi::X509_CRL_get_issuer(self.as_ptr())) }
+    }
+
+    pub fn last_update(&self) -> &Asn1TimeRef {
+        unsafe { Asn1TimeRef::from_ptr(ffi::X509_CRL_get0_lastUpdate(self.as_ptr()) as *mut _) }
+    }
+
+    pub fn next_update(&self) -> Option<&Asn1TimeRef> {
+        unsafe {
+            let time = ffi::X509_CRL_get0_nextUpdate(self.as_ptr());
+            (!time.is_null()).then(|| Asn1TimeRef::from_ptr(time as *mut _))
+        }
+    }
+
+    pub fn crl_number(&self) -> Option<BigNum> {
+        unsafe {
+            let number = ffi::X509_CRL_get_ext_d2i(
+                self.as_ptr(),
+                ffi::NID_crl_number,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            ) as *mut ffi::ASN1_INTEGER;
+            if number.is_null() {
+                return None;
+            }
+            let bn = ffi::ASN1_INTEGER_to_BN(number, ptr::null_mut());
+            ffi::ASN1_INTEGER_free(number);
+            (!bn.is_null()).then(|| BigNum::from_ptr(bn))
+        }
+    }
+
+    pub fn verify<T: HasPublic>(&self, key: &PKey<T>) -> bool {
+        let valid = unsafe { ffi::X509_CRL_verify(self.as_ptr(), key.as_ptr()) } == 1;
+        if !valid {
+            // Greske provjere ostaju u OpenSSL-ovom stogu gresaka
+            ErrorStack::get();
+        }
+        valid
+    }
+
+    pub fn revoked_certificates(&self) -> Result<Vec<RevokedCertificate>> {
+        unsafe {
+            let stack = ffi::X509_CRL_get_REVOKED(self.as_ptr()) as *const ffi::OPENSSL_STACK;
+            if stack.is_null() {
+                return Ok(Vec::new());
+            }
+            (0..ffi::OPENSSL_sk_num(stack))
+                .map(|i| revoked_entry(ffi::OPENSSL_sk_value(stack, i) as *mut _))
+                .collect()
+        }
+    }
+
+    // Opoziv certifikata sa zadanim serijskim brojem, ako je u listi
+    pub fn find(&self, serial_number: &BigNumRef) -> Result<Option<RevokedCertificate>> {
+        unsafe {
+            let serial = cvt_p(ffi::BN_to_ASN1_INTEGER(
+                serial_number.as_ptr(),
+                ptr::null_mut(),
+            ))?;
+            let mut revoked = ptr::null_mut();
+            let found = ffi::X509_CRL_get0_by_serial(self.as_ptr(), &mut revoked, serial);
+            ffi::ASN1_INTEGER_free(serial);
+            match found {
+                1 => Ok(Some(revoked_entry(revoked)?)),
+                _ => Ok(None),
+            }
+        }
+    }
+}
+
+// PEM ili DER zapis
+pub fn load_crl(data: &[u8]) -> Result<X509Crl> {
+    let invalid = || anyhow!("Datoteka ne sadrzi listu opozvanih certifikata (CRL)");
+    let text = String::from_utf8_lossy(data);
+    match text.find(CRL_PEM_BEGIN) {
+        Some(begin) => {
+            let body = &text[begin + CRL_PEM_BEGIN.len()..];
+            let end = body.find(CRL_PEM_END).ok_or_else(invalid)?;
+            let encoded: String = body[..end].split_whitespace().collect();
+            X509Crl::from_der(&decode_block(&encoded).map_err(|_| invalid())?)
+        }
+        None => X509Crl::from_der(data),
+    }
+    .map_err(|_| invalid())
+}
+
+pub fn describe_crl(crl: &X509CrlRef) -> Result<String> {
+    let revoked = crl.revoked_certificates()?;
+    let mut lines = vec![
+        "Lista opozvanih certifikata (CRL)".to_string(),
+        format!("Izdavatelj: {}", name_to_string(crl.issuer_name())),
+    ];
+    if let Some(number) = crl.crl_number() {
+        lines.push(format!("Broj liste: {}", number));
+    }
+    lines.push(format!("Izdana: {}", crl.last_update()));
+    if let Some(next_update) = crl.next_update() {
+        lines.push(format!("Sljedeca lista: {}", next_update));
+    }
+    lines.push(format!("Opozvanih certifikata: {}", revoked.len()));
+    for entry in revoked {
+        lines.push(format!(
+            "  {}  {}",
+            entry.serial_number.to_hex_str()?,
+            entry
+        ));
+    }
+    Ok(lines.join("\n"))
+}
+
+unsafe fn set_revoked(revoked: *mut ffi::X509_REVOKED, entry: &RevokedCertificate) -> Result<()> {
+    let serial = cvt_p(ffi::BN_to_ASN1_INTEGER(
+        entry.serial_number.as_ptr(),
+        ptr::null_mut(),
+    ))?;
+    let set = ffi::X509_REVOKED_set_serialNumber(revoked, serial);
+    ffi::ASN1_INTEGER_free(serial);
+    cvt(set)?;
+    let date = Asn1Time::from_unix(entry.revocation_date.try_into()?)?;
+    cvt(ffi::X509_REVOKED_set_revocationDate(revoked, date.as_ptr()))?;
+    if entry.reason == RevocationReason::Unspecified {
+        return Ok(());
+    }
+    // CRLReason je ENUMERATED, a ASN1_ENUMERATED je ASN1_STRING tog tipa
+    let reason = cvt_p(ffi::ASN1_STRING_type_new(ffi::V_ASN1_ENUMERATED))?;
+    let code = [entry.reason.code()];
+    let mut set = ffi::ASN1_STRING_set(reason, code.as_ptr() as *const _, 1);
+    if set > 0 {
+        set = ffi::X509_REVOKED_add1_ext_i2d(revoked, ffi::NID_crl_reason, reason as *mut _, 0, 0);
+    }
+    ffi::ASN1_STRING_free(reason);
+    cvt(set)?;
+    Ok(())
+}
+
+unsafe fn revoked_entry(revoked: *mut ffi::X509_REVOKED) -> Result<RevokedCertificate> {
+    let serial = cvt_p(ffi::ASN1_INTEGER_to_BN(
+        ffi::X509_REVOKED_get0_serialNumber(revoked),
+        ptr::null_mut(),
+    ))?;
+    let serial_number = BigNum::from_ptr(serial);
+    let date = Asn1TimeRef::from_ptr(ffi::X509_REVOKED_get0_revocationDate(revoked) as *mut _);
+
+    let reason = ffi::X509_REVOKED_get_ext_d2i(
+        revoked,
+        ffi::NID_crl_reason,
+        ptr::null_mut(),
+        ptr::null_mut(),
+    ) as *mut ffi::ASN1_STRING;
+    // Neispravno zapisan razlog ne smije sakriti sam opoziv
+    let reason = if reason.is_null() {
+        RevocationReason::Unspecified
+    } else {
+        let code = match ffi::ASN1_STRING_length(reason) {
+            1 => RevocationReason::from_code(*ffi::ASN1_STRING_get0_data(reason)),
+            _ => RevocationReason::Unspecified,
+        };
+        ffi::ASN1_STRING_free(reason);
+        code
+    };
+    Ok(RevokedCertificate {
+        serial_number,
+        revocation_date: unix_time(date)?,
+        reason,
+    })
+}
+
+pub(crate) fn cvt(result: c_int) -> Result<c_int, ErrorStack> {
+    match result {
+        result if result <= 0 => Err(ErrorStack::get()),
+        result => Ok(result),
+    }
+}
+
+pub(crate) fn cvt_p<T>(pointer: *mut T) -> Result<*mut T, ErrorStack> {
+    match pointer.is_null() {
+        true => Err(ErrorStack::get()),
+        false => Ok(pointer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificate::{create_self_signed_certificate, CertificateParams};
+    use openssl::{ec::EcGroup, ec::EcKey, nid::Nid};
+
+    #[test]
+    fn crl_keeps_revocation_dates_and_reasons() {
+        let p256 = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let keys = [
+            PKey::generate_ed25519().unwrap(),
+            PKey::from_ec_key(EcKey::generate(&p256).unwrap()).unwrap(),
+        ];
+        let params = CertificateParams {
+            subject: "CN=OS2 CRL CA".to_string(),
+            certificate_authority: true,
+            ..CertificateParams::default()
+        };
+        for key in keys {
+            let issuer = create_self_signed_certificate(&key, &params).unwrap();
+            let revoked: Vec<RevokedCertificate> = RevocationReason::ALL
+                .into_iter()
+                .enumerate()
+                .map(|(i, reason)| RevokedCertificate {
+                    serial_number: BigNum::from_u32(0x1001 + i as u32).unwrap(),
+                    revocation_date: 1_700_000_000 + i as u64,
+                    reason,
+                })
+                .collect();
+            let number = BigNum::from_u32(7).unwrap();
+            let crl = X509Crl::create(&issuer, &key, &revoked, &number, 30).unwrap();
+            assert!(crl.verify(&key));
+            assert!(!crl.verify(&PKey::generate_ed25519().unwrap()));
+
+            for data in [crl.to_pem().unwrap(), crl.to_der().unwrap()] {
+                let loaded = load_crl(&data).unwrap();
+                assert_eq!(loaded.to_der().unwrap(), crl.to_der().unwrap());
+                assert_eq!(loaded.crl_number().unwrap(), number);
+                assert!(loaded.next_update().is_some());
+                assert_eq!(
+                    name_to_string(loaded.issuer_name()),
+                    name_to_string(issuer.subject_name())
+                );
+                assert_eq!(loaded.revoked_certificates().unwrap().len(), revoked.len());
+                for expected in &revoked {
+                    let found = loaded.find(&expected.serial_number).unwrap().unwrap();
+                    assert_eq!(found.revocation_date, expected.revocation_date);
+                    assert_eq!(found.reason, expected.reason);
+                }
+            }
+            let missing = BigNum::from_u32(0x2001).unwrap();
+            assert!(crl.find(&missing).unwrap().is_none());
+
+            let description = describe_crl(&crl).unwrap();
+            assert!(description.contains("Izdavatelj: CN=OS2 CRL CA"));
+            assert!(description.contains("Broj liste: 7"));
+            assert!(description
+                .contains("1002  opozvan 2023-11-14 22:13:21 UTC, razlog: kompromitiran kljuc"));
+        }
+        assert!(load_crl(b"nije lista").is_err());
+    }
+
+    #[test]
+    fn suspended_and_unknown_reasons_are_read() {
+        let key = PKey::generate_ed25519().unwrap();
+        let params = CertificateParams {
+            subject: "CN=OS2 CRL CA".to_string(),
+            certificate_authority: true,
+            ..CertificateParams::default()
+        };
+        let issuer = create_self_signed_certificate(&key, &params).unwrap();
+        let reasons = [
+            RevocationReason::CertificateHold,
+            RevocationReason::RemoveFromCrl,
+            RevocationReason::Unknown(7),
+        ];
+        let revoked: Vec<RevokedCertificate> = reasons
+            .into_iter()
+            .enumerate()
+            .map(|(i, reason)| RevokedCertificate {
+                serial_number: BigNum::from_u32(0x3001 + i as u32).unwrap(),
+                revocation_date: 1_700_000_000,
+                reason,
+            })
+            .collect();
+        let number = BigNum::from_u32(1).unwrap();
+        let crl = X509Crl::create(&issuer, &key, &revoked, &number, 30).unwrap();
+
+        let loaded = load_crl(&crl.to_der().unwrap()).unwrap();
+        let entries = loaded.revoked_certificates().unwrap();
+        assert_eq!(
+            entries.iter().map(|entry| entry.reason).collect::<Vec<_>>(),
+            reasons
+        );
+        let hold = loaded.find(&revoked[0].serial_number).unwrap().unwrap();
+        assert_eq!(hold.reason.to_string(), "privremeno suspendiran");
+        assert!(describe_crl(&loaded)
+            .unwrap()
+            .contains("razlog: nepoznat razlog (7)"));
+    }
+
+    #[test]
+    fn reason_names_round_trip() {
+        for reason in RevocationReason::ALL.into_iter().chain([
+            RevocationReason::RemoveFromCrl,
+            RevocationReason::Unknown(42),
+        ]) {
+            assert_eq!(RevocationReason::from_name(&reason.name()).unwrap(), reason);
+        }
+        assert!(RevocationReason::from_name("nepoznato").is_err());
+    }
+}
@@ -1,23 +1,89 @@
 use openssl::{
-    pkey::Private,
+    bn::BigNum,
+    encrypt::{Decrypter, Encrypter},
+    hash::MessageDigest,
+    pkey::{HasPublic, PKey, Private, Public},
     rsa::{Padding, Rsa},
     sha::Sha256,
-    symm::{decrypt, encrypt, Cipher},
+    symm::{decrypt, decrypt_aead, encrypt, encrypt_aead, Cipher, Crypter, Mode},
 };
 
 use crate::{
-    file_manip::{read_file_to_buffer, write_file},
-    PRIVATE_KEY_FILENAME, PUBLIC_KEY_FILENAME,
+    file_container::{
+        read_length_prefixed, write_length_prefixed, Container, ContainerCipher, KeyWrap,
+    },
+    file_manip::{
+        read_chunks, read_file_to_buffer, write_file, write_file_streamed, STREAM_BUFFER_SIZE,
+    },
+    fingerprint::key_fingerprint,
+    kdf::{Kdf, KdfParams},
+    key_format::import_public_key,
+    keygen::Keygen,
+    private_key::{load_private_key, save_private_key},
+    signing::{sign_with_algorithm, verify_with_algorithm, SignatureAlgorithm},
+    PUBLIC_KEY_FILENAME, SECRET_KEY_FILENAME,
 };
 
 use anyhow::{anyhow, Result};
+use std::{
+    fmt,
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+};
 
 const RSA_KEY_LENGTH: u32 = 2048;
+const RSA_PUBLIC_EXPONENT: u32 = 65537;
+const SECRET_KEY_LENGTH: usize = 32;
+const AEAD_NONCE_LENGTH: usize = 12;
+pub const AEAD_TAG_LENGTH: usize = 16;
+pub const FINGERPRINT_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AeadAlgorithm {
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl AeadAlgorithm {
+    pub const ALL: [AeadAlgorithm; 2] = [AeadAlgorithm::Aes256Gcm, AeadAlgorithm::ChaCha20Poly1305];
+
+    pub fn cipher(&self) -> Cipher {
+        match self {
+            AeadAlgorithm::Aes256Gcm => Cipher::aes_256_gcm(),
+            AeadAlgorithm::ChaCha20Poly1305 => Cipher::chacha20_poly1305(),
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            AeadAlgorithm::Aes256Gcm => 1,
+            AeadAlgorithm::ChaCha20Poly1305 => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Result<Self> {
+        match id {
+            1 => Ok(AeadAlgorithm::Aes256Gcm),
+            2 => Ok(AeadAlgorithm::ChaCha20Poly1305),
+            _ => Err(anyhow!("Nepoznat AEAD algoritam ({})", id)),
+        }
+    }
+}
+
+impl fmt::Display for AeadAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AeadAlgorithm::Aes256Gcm => write!(f, "AES-256-GCM"),
+            AeadAlgorithm::ChaCha20Poly1305 => write!(f, "ChaCha20-Poly1305"),
+        }
+    }
+}
 
 pub struct EncryptAes {
     cipher: Cipher,
     key: Vec<u8>,
     initialization_vector: Option<Vec<u8>>,
+    aead: Option<AeadAlgorithm>,
 }
 
 impl EncryptAes {
@@ -26,10 +92,30 @@ impl EncryptAes {
             cipher,
             key,
             initialization_vector,
+            aead: None,
+        }
+    }
+
+    // Svaka enkripcija koristi novi slucajni nonce koji se zapisuje ispred kriptiranog teksta:
+    // [nonce][kriptirani tekst][oznaka autentifikacije]
+    pub fn new_aead(algorithm: AeadAlgorithm, key: Vec<u8>) -> Self {
+        Self {
+            cipher: algorithm.cipher(),
+            key,
+            initialization_vector: None,
+            aead: Some(algorithm),
         }
     }
 
     pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if self.aead.is_some() {
+            let (nonce, encrypted_data, tag) = self.encrypt_aead(self.cipher, &[], data)?;
+            let mut out = Vec::with_capacity(nonce.len() + encrypted_data.len() + tag.len());
+            out.extend_from_slice(&nonce);
+            out.extend_from_slice(&encrypted_data);
+            out.extend_from_slice(&tag);
+            return Ok(out);
+        }
         Ok(encrypt(
             self.cipher,
             &self.key,
@@ -39,6 +125,14 @@ impl EncryptAes {
     }
 
     pub fn decrypt(&self, encrypted_data: &[u8]) -> Result<Vec<u8>> {
+        if self.aead.is_some() {
+            if encrypted_data.len() < AEAD_NONCE_LENGTH + AEAD_TAG_LENGTH {
+                return Err(anyhow!("Neispravan format kriptiranih podataka"));
+            }
+            let (nonce, rest) = encrypted_data.split_at(AEAD_NONCE_LENGTH);
+            let (encrypted_data, tag) = rest.split_at(rest.len() - AEAD_TAG_LENGTH);
+            return self.decrypt_aead(self.cipher, &[], nonce, encrypted_data, tag);
+        }
         Ok(decrypt(
             self.cipher,
             &self.key,
@@ -47,85 +141,350 @@ impl EncryptAes {
         )?)
     }
 
-    pub fn encrypt_file(&self, filename: &str) -> Result<Vec<u8>> {
-        let file = read_file_to_buffer(filename)?;
-        self.encrypt(&file)
+    fn encrypt_aead(
+        &self,
+        cipher: Cipher,
+        aad: &[u8],
+        data: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>)> {
+        let nonce = Keygen::default().generate_96bit_nonce();
+        let mut tag = vec![0; AEAD_TAG_LENGTH];
+        let encrypted_data = encrypt_aead(cipher, &self.key, Some(&nonce), aad, data, &mut tag)?;
+        Ok((nonce.to_vec(), encrypted_data, tag))
     }
 
-    pub fn decrypt_file(&self, filename: &str) -> Result<Vec<u8>> {
-        let file = read_file_to_buffer(filename)?;
-        self.decrypt(&file)
+    fn decrypt_aead(
+        &self,
+        cipher: Cipher,
+        aad: &[u8],
+        nonce: &[u8],
+        encrypted_data: &[u8],
+        tag: &[u8],
+    ) -> Result<Vec<u8>> {
+        decrypt_aead(cipher, &self.key, Some(nonce), aad, encrypted_data, tag)
+            .map_err(|_| authentication_error())
     }
-}
 
-pub struct EncryptRsa {
-    rsa: Rsa<Private>,
-}
+    pub fn new_container(&self, key_wrap: KeyWrap) -> Result<Container> {
+        let cipher = match self.aead {
+            Some(AeadAlgorithm::Aes256Gcm) => ContainerCipher::Aes256Gcm,
+            Some(AeadAlgorithm::ChaCha20Poly1305) => ContainerCipher::ChaCha20Poly1305,
+            None if self.cipher.nid() == Cipher::aes_256_cbc().nid() => ContainerCipher::Aes256Cbc,
+            None => return Err(anyhow!("Algoritam nije podrzan u spremniku")),
+        };
+        Ok(Container::new(cipher, key_wrap))
+    }
 
-impl EncryptRsa {
-    pub fn new_save_keys() -> Result<Self> {
-        let rsa = Rsa::generate(RSA_KEY_LENGTH)?;
-        write_file(PRIVATE_KEY_FILENAME, &rsa.private_key_to_pem()?, false)?;
-        write_file(PUBLIC_KEY_FILENAME, &rsa.public_key_to_pem()?, false)?;
-        Ok(Self { rsa })
+    pub fn encrypt_into_container(&self, container: &mut Container, data: &[u8]) -> Result<()> {
+        match container.cipher {
+            ContainerCipher::Aes256Cbc => {
+                let initialization_vector = Keygen::default().generate_128bit_key();
+                container.payload = EncryptAes::new(
+                    Cipher::aes_256_cbc(),
+                    self.key.clone(),
+                    Some(initialization_vector.to_vec()),
+                )
+                .encrypt(data)?;
+                container.nonce = initialization_vector.to_vec();
+            }
+            ContainerCipher::Aes256Gcm | ContainerCipher::ChaCha20Poly1305 => {
+                let cipher = aead_cipher(container.cipher)?;
+                let (nonce, encrypted_data, tag) =
+                    self.encrypt_aead(cipher, &container.header(), data)?;
+                container.nonce = nonce;
+                container.payload = encrypted_data;
+                container.tag = tag;
+            }
+            ContainerCipher::RsaPkcs1 => {
+                return Err(anyhow!("Algoritam nije simetricni algoritam"));
+            }
+        }
+        Ok(())
     }
 
-    pub fn from_files(filename: Option<&str>) -> Result<Self> {
-        let private_key_pem = match read_file_to_buffer(filename.unwrap_or(PRIVATE_KEY_FILENAME)) {
-            Ok(pem) => pem,
-            Err(error) => {
-                return Err(anyhow!("Ne postoji par kljuceva | {:?}", error));
+    // Algoritam i parametri se citaju iz spremnika, koristi se samo kljuc
+    pub fn decrypt_container(&self, container: &Container) -> Result<Vec<u8>> {
+        match container.cipher {
+            ContainerCipher::Aes256Cbc => {
+                let initialization_vector = if container.nonce.is_empty() {
+                    None
+                } else {
+                    Some(container.nonce.clone())
+                };
+                EncryptAes::new(
+                    Cipher::aes_256_cbc(),
+                    self.key.clone(),
+                    initialization_vector,
+                )
+                .decrypt(&container.payload)
             }
+            ContainerCipher::Aes256Gcm | ContainerCipher::ChaCha20Poly1305 => self.decrypt_aead(
+                aead_cipher(container.cipher)?,
+                &container.header(),
+                &container.nonce,
+                &container.payload,
+                &container.tag,
+            ),
+            ContainerCipher::RsaPkcs1 => Err(anyhow!(
+                "Datoteka nije kriptirana simetricnim algoritmom ({})",
+                container.cipher
+            )),
+        }
+    }
+
+    // Isti zapis kao `encrypt_into_container`, ali podaci se kriptiraju u dijelovima.
+    // Oznaka AEAD algoritma je poznata tek na kraju pa se naknadno upisuje u zaglavlje.
+    pub fn encrypt_stream<R: Read, W: Write + Seek>(
+        &self,
+        container: &mut Container,
+        reader: &mut R,
+        writer: &mut W,
+    ) -> Result<()> {
+        let (cipher, aead) = stream_cipher(container.cipher)?;
+        container.nonce = if aead {
+            container.tag = vec![0; AEAD_TAG_LENGTH];
+            Keygen::default().generate_96bit_nonce().to_vec()
+        } else {
+            Keygen::default().generate_128bit_key().to_vec()
+        };
+        writer.write_all(&container.fields_to_bytes())?;
+
+        let mut crypter = Crypter::new(cipher, Mode::Encrypt, &self.key, Some(&container.nonce))?;
+        if aead {
+            crypter.aad_update(&container.header())?;
+        }
+        let mut out = vec![0; STREAM_BUFFER_SIZE + cipher.block_size()];
+        read_chunks(reader, |chunk| {
+            let length = crypter.update(chunk, &mut out)?;
+            writer.write_all(&out[..length])?;
+            Ok(())
+        })?;
+        let length = crypter.finalize(&mut out)?;
+        writer.write_all(&out[..length])?;
+
+        if aead {
+            crypter.get_tag(&mut container.tag)?;
+            writer.seek(SeekFrom::Start(container.tag_offset()))?;
+            writer.write_all(&container.tag)?;
+            writer.seek(SeekFrom::End(0))?;
+        }
+        Ok(())
+    }
+
+    // Kod AEAD algoritama oznaka se provjerava tek na kraju,
+    // do tada zapisani podaci nisu autentificirani
+    pub fn decrypt_stream<R: Read, W: Write>(
+        &self,
+        container: &Container,
+        reader: &mut R,
+        writer: &mut W,
+    ) -> Result<()> {
+        let (cipher, aead) = stream_cipher(container.cipher)?;
+        let initialization_vector = if container.nonce.is_empty() {
+            None
+        } else {
+            Some(container.nonce.as_slice())
+        };
+        let mut crypter = Crypter::new(cipher, Mode::Decrypt, &self.key, initialization_vector)?;
+        if aead {
+            crypter.aad_update(&container.header())?;
+            crypter.set_tag(&container.tag)?;
+        }
+        let mut out = vec![0; STREAM_BUFFER_SIZE + cipher.block_size()];
+        // Kod starog formata pocetak podataka je vec procitan u spremnik
+        let mut reader = container.payload.as_slice().chain(reader);
+        read_chunks(&mut reader, |chunk| {
+            let length = crypter.update(chunk, &mut out)?;
+            writer.write_all(&out[..length])?;
+            Ok(())
+        })?;
+        let length = if aead {
+            crypter
+                .finalize(&mut out)
+                .map_err(|_| authentication_error())?
+        } else {
+            crypter.finalize(&mut out)?
         };
-        Ok(Self {
-            rsa: Rsa::private_key_from_pem(&private_key_pem)?,
+        writer.write_all(&out[..length])?;
+        Ok(())
+    }
+
+    // Otisak tajnog kljuca zapisuje se u spremnik
+    pub fn key_fingerprint(&self) -> Result<Vec<u8>> {
+        ShaHash::hash(&self.key)
+    }
+
+    pub fn encrypt_file(&self, filename: &str, output_filename: &str) -> Result<()> {
+        let mut reader = File::open(filename)?;
+        let mut container = self.new_container(KeyWrap::None)?;
+        container.key_fingerprint = self.key_fingerprint()?;
+        write_file_streamed(output_filename, |writer| {
+            self.encrypt_stream(&mut container, &mut reader, writer)
         })
     }
 
-    pub fn private_encrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
-        let mut out = Vec::with_capacity(data.len());
-        let mut buf = vec![0; self.rsa.size() as usize];
-        let mut bytes_remaining = data.len();
+    pub fn decrypt_file(&self, filename: &str, output_filename: &str) -> Result<()> {
+        let mut reader = File::open(filename)?;
+        let container = Container::read_from(&mut reader, ContainerCipher::Aes256Cbc)?;
+        if container.key_wrap == KeyWrap::Password {
+            return Err(anyhow!("Datoteka je kriptirana lozinkom"));
+        }
+        if !container.key_fingerprint.is_empty()
+            && container.key_fingerprint != self.key_fingerprint()?
+        {
+            return Err(anyhow!("Datoteka je kriptirana drugim tajnim kljucem"));
+        }
+        write_file_streamed(output_filename, |writer| {
+            self.decrypt_stream(&container, &mut reader, writer)
+        })
+    }
 
-        let mut from = 0;
-        while bytes_remaining > 0 {
-            let to = if bytes_remaining > (self.rsa.size() as usize - 11) {
-                from + self.rsa.size() as usize - 11
-            } else {
-                from + bytes_remaining
-            };
-            self.rsa
-                .private_encrypt(&data[from..to], &mut buf, Padding::PKCS1)?;
+    // Kljuc se izvodi iz lozinke, a sol i parametri se zapisuju u spremnik
+    pub fn encrypt_file_with_password(
+        algorithm: AeadAlgorithm,
+        kdf: Kdf,
+        password: &str,
+        filename: &str,
+        output_filename: &str,
+    ) -> Result<()> {
+        let mut reader = File::open(filename)?;
+        let params = KdfParams::new(kdf);
+        let aes = EncryptAes::new_aead(algorithm, params.derive_key(password)?);
+        let mut container = aes.new_container(KeyWrap::Password)?;
+        container.kdf_params = params.to_bytes();
+        container.key_fingerprint = aes.key_fingerprint()?;
+        write_file_streamed(output_filename, |writer| {
+            aes.encrypt_stream(&mut container, &mut reader, writer)
+        })
+    }
 
-            bytes_remaining -= to - from;
-            from = to;
-            out.append(&mut buf);
-            fill_buffer(&mut buf);
+    pub fn decrypt_file_with_password(
+        password: &str,
+        filename: &str,
+        output_filename: &str,
+    ) -> Result<()> {
+        let mut reader = File::open(filename)?;
+        let container = Container::read_from(&mut reader, ContainerCipher::Aes256Cbc)?;
+        if container.key_wrap != KeyWrap::Password {
+            return Err(anyhow!("Datoteka nije kriptirana lozinkom"));
         }
-        Ok(out)
+        let key = KdfParams::from_bytes(&container.kdf_params)?.derive_key(password)?;
+        if container.key_fingerprint != ShaHash::hash(&key)? {
+            return Err(anyhow!("Pogresna lozinka"));
+        }
+        // Algoritam se cita iz spremnika
+        let aes = EncryptAes::new_aead(AeadAlgorithm::Aes256Gcm, key);
+        write_file_streamed(output_filename, |writer| {
+            aes.decrypt_stream(&container, &mut reader, writer)
+        })
     }
+}
 
-    pub fn public_encrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
-        let mut out = Vec::with_capacity(data.len());
-        let mut buf = vec![0; self.rsa.size() as usize];
-        let mut bytes_remaining = data.len();
+fn authentication_error() -> anyhow::Error {
+    anyhow!("Autentifikacija nije uspjela (podaci su izmijenjeni ili je kljuc pogresan)")
+}
 
-        let mut from = 0;
-        while bytes_remaining > 0 {
-            let to = if bytes_remaining > (self.rsa.size() as usize - 11) {
-                from + self.rsa.size() as usize - 11
-            } else {
-                from + bytes_remaining
-            };
-            self.rsa
-                .public_encrypt(&data[from..to], &mut buf, Padding::PKCS1)?;
+// Simetricni algoritam spremnika i je li AEAD
+fn stream_cipher(cipher: ContainerCipher) -> Result<(Cipher, bool)> {
+    match cipher {
+        ContainerCipher::Aes256Cbc => Ok((Cipher::aes_256_cbc(), false)),
+        ContainerCipher::Aes256Gcm | ContainerCipher::ChaCha20Poly1305 => {
+            Ok((aead_cipher(cipher)?, true))
+        }
+        ContainerCipher::RsaPkcs1 => Err(anyhow!(
+            "Datoteka nije kriptirana simetricnim algoritmom ({})",
+            cipher
+        )),
+    }
+}
 
-            bytes_remaining -= to - from;
-            from = to;
-            out.append(&mut buf);
-            fill_buffer(&mut buf);
+// Najmanja velicina kljuca koja se moze generirati ili zadati kao minimum kod ucitavanja
+pub const MIN_RSA_KEY_LENGTH: u32 = RSA_KEY_LENGTH;
+pub const RSA_KEY_LENGTHS: [u32; 3] = [2048, 3072, 4096];
+
+// Kljucevi manji od minimalne velicine se odbijaju kod ucitavanja
+pub fn check_rsa_key_length(bits: u32, min: u32) -> Result<()> {
+    if bits < min {
+        return Err(anyhow!(
+            "RSA kljuc ima {} bita, a minimalna dopustena velicina je {} bita",
+            bits,
+            min
+        ));
+    }
+    Ok(())
+}
+
+// Javni eksponent je uvijek 65537
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RsaKeyParams {
+    pub bits: u32,
+}
+
+impl Default for RsaKeyParams {
+    fn default() -> Self {
+        Self {
+            bits: RSA_KEY_LENGTH,
         }
-        Ok(out)
+    }
+}
+
+pub fn generate_rsa_key(params: RsaKeyParams) -> Result<Rsa<Private>> {
+    check_rsa_key_length(params.bits, MIN_RSA_KEY_LENGTH)?;
+    let exponent = BigNum::from_u32(RSA_PUBLIC_EXPONENT)?;
+    Ok(Rsa::generate_with_e(params.bits, &exponent)?)
+}
+
+fn aead_cipher(cipher: ContainerCipher) -> Result<Cipher> {
+    match cipher {
+        ContainerCipher::Aes256Gcm => Ok(AeadAlgorithm::Aes256Gcm.cipher()),
+        ContainerCipher::ChaCha20Poly1305 => Ok(AeadAlgorithm::ChaCha20Poly1305.cipher()),
+        _ => Err(anyhow!("Algoritam {} nije AEAD algoritam", cipher)),
+    }
+}
+
+pub struct EncryptRsa {
+    rsa: Rsa<Private>,
+    oaep_label: Vec<u8>,
+    allow_legacy: bool,
+}
+
+impl EncryptRsa {
+    fn new(rsa: Rsa<Private>) -> Self {
+        Self {
+            rsa,
+            oaep_label: Vec::new(),
+            allow_legacy: false,
+        }
+    }
+
+    pub fn new_save_keys(params: RsaKeyParams, passphrase: Option<&str>) -> Result<Self> {
+        let rsa = generate_rsa_key(params)?;
+        save_private_key(&PKey::from_rsa(rsa.clone())?, None, passphrase)?;
+        write_file(PUBLIC_KEY_FILENAME, &rsa.public_key_to_pem()?, false)?;
+        Ok(Self::new(rsa))
+    }
+
+    // Lozinka se koristi samo za kljuc zasticen lozinkom
+    pub fn from_files(
+        filename: Option<&str>,
+        passphrase: Option<&str>,
+        min_rsa_key_length: u32,
+    ) -> Result<Self> {
+        let rsa = load_private_key(filename, passphrase)?
+            .rsa()
+            .map_err(|_| anyhow!("Par kljuceva nije RSA par kljuceva"))?;
+        check_rsa_key_length(rsa.size() * 8, min_rsa_key_length)?;
+        Ok(Self::new(rsa))
+    }
+
+    // Oznaka se zapisuje u spremnik i kod dekripcije se cita iz njega
+    pub fn set_oaep_label(&mut self, label: &[u8]) {
+        self.oaep_label = label.to_vec();
+    }
+
+    // Dekripcija starog formata (RSA PKCS#1 v1.5 po blokovima) mora biti eksplicitno dopustena
+    pub fn set_allow_legacy(&mut self, allow: bool) {
+        self.allow_legacy = allow;
     }
 
     pub fn private_decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
@@ -152,70 +511,289 @@ impl EncryptRsa {
         Ok(out)
     }
 
-    pub fn public_decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
-        let mut out = Vec::with_capacity(data.len());
-        let mut buf = vec![0; self.rsa.size() as usize];
-        let mut bytes_remaining = data.len();
+    pub fn key_fingerprint(&self) -> Result<Vec<u8>> {
+        key_fingerprint(&PKey::from_rsa(self.rsa.clone())?)
+    }
 
-        let mut from = 0;
-        while bytes_remaining > 0 {
-            let to = if bytes_remaining > (self.rsa.size() as usize) {
-                from + self.rsa.size() as usize
-            } else {
-                from + bytes_remaining
-            };
-            let bytes_decrypted =
-                self.rsa
-                    .public_decrypt(&data[from..to], &mut buf, Padding::PKCS1)?;
+    pub fn hybrid_decrypt(&self, container: &Container) -> Result<Vec<u8>> {
+        if container.cipher == ContainerCipher::RsaPkcs1 {
+            self.check_legacy()?;
+            return self.private_decrypt(&container.payload);
+        }
+        self.session_cipher(container)?.decrypt_container(container)
+    }
 
-            bytes_remaining -= to - from;
-            from = to;
-            out.append(&mut buf[..bytes_decrypted].to_vec());
-            fill_buffer(&mut buf);
+    // Sjednicki kljuc iz spremnika, otisak kljuca mora odgovarati ovom paru kljuceva
+    fn session_cipher(&self, container: &Container) -> Result<EncryptAes> {
+        if !container.key_fingerprint.is_empty()
+            && container.key_fingerprint != self.key_fingerprint()?
+        {
+            return Err(anyhow!("Datoteka je kriptirana drugim javnim kljucem"));
         }
-        Ok(out)
+        let session_key = match (container.key_wrap, container.wrapped_key.as_deref()) {
+            (KeyWrap::RsaOaepSha256, Some(encrypted_key)) => {
+                oaep_unwrap(&self.rsa, encrypted_key, &container.oaep_label)?
+            }
+            (KeyWrap::RsaPkcs1, Some(encrypted_key)) => {
+                self.check_legacy()?;
+                self.private_decrypt(encrypted_key)?
+            }
+            _ => return Err(anyhow!("Datoteka nije kriptirana javnim kljucem")),
+        };
+        Ok(EncryptAes::new_aead(AeadAlgorithm::Aes256Gcm, session_key))
     }
 
-    pub fn private_encrypt_file(&self, filename: &str) -> Result<Vec<u8>> {
-        let file = read_file_to_buffer(filename)?;
-        self.private_encrypt(&file)
+    fn check_legacy(&self) -> Result<()> {
+        if !self.allow_legacy {
+            return Err(anyhow!(
+                "Datoteka je u starom formatu (RSA PKCS#1 v1.5), potrebno je dopustiti stari format"
+            ));
+        }
+        Ok(())
     }
 
-    pub fn private_decrypt_file(&self, filename: &str) -> Result<Vec<u8>> {
-        let file = read_file_to_buffer(filename)?;
-        self.private_decrypt(&file)
+    pub fn private_decrypt_file(&self, filename: &str, output_filename: &str) -> Result<()> {
+        let mut reader = File::open(filename)?;
+        let mut container = Container::read_from(&mut reader, ContainerCipher::RsaPkcs1)?;
+        if container.cipher == ContainerCipher::RsaPkcs1 {
+            // Stari format je RSA po blokovima bez spremnika, cita se cijeli
+            reader.read_to_end(&mut container.payload)?;
+            return write_file(output_filename, &self.hybrid_decrypt(&container)?, false);
+        }
+        let aes = self.session_cipher(&container)?;
+        write_file_streamed(output_filename, |writer| {
+            aes.decrypt_stream(&container, &mut reader, writer)
+        })
     }
 
-    pub fn public_encrypt_file(&self, filename: &str) -> Result<Vec<u8>> {
-        let file = read_file_to_buffer(filename)?;
-        self.public_encrypt(&file)
+    pub fn public_encrypt_file(&self, filename: &str, output_filename: &str) -> Result<()> {
+        let mut reader = File::open(filename)?;
+        let (aes, mut container) = hybrid_session(&self.rsa, &self.oaep_label)?;
+        write_file_streamed(output_filename, |writer| {
+            aes.encrypt_stream(&mut container, &mut reader, writer)
+        })
     }
+}
 
-    pub fn public_decrypt_file(&self, filename: &str) -> Result<Vec<u8>> {
-        let file = read_file_to_buffer(filename)?;
-        self.public_decrypt(&file)
+pub fn load_secret_key(filename: Option<&str>) -> Result<Vec<u8>> {
+    let key = match read_file_to_buffer(filename.unwrap_or(SECRET_KEY_FILENAME)) {
+        Ok(key) => key,
+        Err(error) => {
+            return Err(anyhow!(
+                "Ucitavanje tajnog kljuca nije uspijelo | {:?}",
+                error
+            ));
+        }
+    };
+    if key.len() != SECRET_KEY_LENGTH {
+        return Err(anyhow!(
+            "Tajni kljuc mora imati {} bajta, a ucitani kljuc ima {}",
+            SECRET_KEY_LENGTH,
+            key.len()
+        ));
     }
+    Ok(key)
+}
 
-    pub fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
-        let hash = ShaHash::hash(data)?;
-        self.private_encrypt(&hash)
+pub fn load_public_key(filename: Option<&str>, min_rsa_key_length: u32) -> Result<Rsa<Public>> {
+    let public_key = match read_file_to_buffer(filename.unwrap_or(PUBLIC_KEY_FILENAME)) {
+        Ok(key) => key,
+        Err(error) => {
+            return Err(anyhow!("Ne postoji javni kljuc | {:?}", error));
+        }
+    };
+    let rsa = import_public_key(&public_key)?
+        .rsa()
+        .map_err(|_| anyhow!("Javni kljuc nije RSA kljuc"))?;
+    check_rsa_key_length(rsa.size() * 8, min_rsa_key_length)?;
+    Ok(rsa)
+}
+
+// digitalna_omotnica = { E(m,K); E(K,PB) }
+// Zapisuje se kao spremnik: E(K,PB) je zasticeni kljuc (RSA-OAEP), E(m,K) su podaci (AES-256-GCM)
+pub struct DigitalEnvelope;
+
+impl DigitalEnvelope {
+    pub fn create(data: &[u8], recipient: &Rsa<Public>, label: &[u8]) -> Result<Vec<u8>> {
+        Ok(hybrid_encrypt(data, recipient, label)?.to_bytes())
     }
 
-    pub fn sign_file(&self, filename: &str) -> Result<Vec<u8>> {
+    pub fn open(envelope: &[u8], recipient: &EncryptRsa) -> Result<Vec<u8>> {
+        let container = Container::from_bytes(envelope)?;
+        if container.wrapped_key.is_none() {
+            return Err(anyhow!("Datoteka nije digitalna omotnica"));
+        }
+        recipient.hybrid_decrypt(&container)
+    }
+
+    pub fn create_file(filename: &str, recipient: &Rsa<Public>, label: &[u8]) -> Result<Vec<u8>> {
         let data = read_file_to_buffer(filename)?;
-        self.sign(&data)
+        DigitalEnvelope::create(&data, recipient, label)
     }
 
-    pub fn verify_file_signature(&self, filename: &str, signature_filename: &str) -> Result<bool> {
-        let file = read_file_to_buffer(filename)?;
-        let sig = read_file_to_buffer(signature_filename)?;
-        let hash = ShaHash::hash(&file)?;
-        self.verify(&hash, &sig)
+    pub fn open_file(filename: &str, recipient: &EncryptRsa) -> Result<Vec<u8>> {
+        let envelope = read_file_to_buffer(filename)?;
+        DigitalEnvelope::open(&envelope, recipient)
     }
+}
 
-    pub fn verify(&self, hash: &[u8], signature: &[u8]) -> Result<bool> {
-        let original_data_hash = self.public_decrypt(signature)?;
-        Ok(original_data_hash == hash)
+fn hybrid_encrypt<T: HasPublic>(
+    data: &[u8],
+    recipient: &Rsa<T>,
+    label: &[u8],
+) -> Result<Container> {
+    let (aes, mut container) = hybrid_session(recipient, label)?;
+    aes.encrypt_into_container(&mut container, data)?;
+    Ok(container)
+}
+
+// Novi sjednicki kljuc i spremnik u kojem je kljuc zasticen javnim kljucem primatelja
+fn hybrid_session<T: HasPublic>(
+    recipient: &Rsa<T>,
+    label: &[u8],
+) -> Result<(EncryptAes, Container)> {
+    let session_key = Keygen::default().generate_256bit_key();
+    let aes = EncryptAes::new_aead(AeadAlgorithm::Aes256Gcm, session_key.to_vec());
+    let mut container = aes.new_container(KeyWrap::RsaOaepSha256)?;
+    container.wrapped_key = Some(oaep_wrap(recipient, &session_key, label)?);
+    container.oaep_label = label.to_vec();
+    container.key_fingerprint = key_fingerprint(&PKey::from_rsa(recipient.clone())?)?;
+    Ok((aes, container))
+}
+
+fn oaep_wrap<T: HasPublic>(rsa: &Rsa<T>, key: &[u8], label: &[u8]) -> Result<Vec<u8>> {
+    let pkey = PKey::from_rsa(rsa.clone())?;
+    let mut encrypter = Encrypter::new(&pkey)?;
+    encrypter.set_rsa_padding(Padding::PKCS1_OAEP)?;
+    encrypter.set_rsa_oaep_md(MessageDigest::sha256())?;
+    encrypter.set_rsa_mgf1_md(MessageDigest::sha256())?;
+    if !label.is_empty() {
+        encrypter.set_rsa_oaep_label(label)?;
+    }
+    let mut out = vec![0; encrypter.encrypt_len(key)?];
+    let length = encrypter.encrypt(key, &mut out)?;
+    out.truncate(length);
+    Ok(out)
+}
+
+fn oaep_unwrap(rsa: &Rsa<Private>, wrapped_key: &[u8], label: &[u8]) -> Result<Vec<u8>> {
+    let pkey = PKey::from_rsa(rsa.clone())?;
+    let mut decrypter = Decrypter::new(&pkey)?;
+    decrypter.set_rsa_padding(Padding::PKCS1_OAEP)?;
+    decrypter.set_rsa_oaep_md(MessageDigest::sha256())?;
+    decrypter.set_rsa_mgf1_md(MessageDigest::sha256())?;
+    if !label.is_empty() {
+        decrypter.set_rsa_oaep_label(label)?;
+    }
+    let mut out = vec![0; decrypter.decrypt_len(wrapped_key)?];
+    let length = decrypter
+        .decrypt(wrapped_key, &mut out)
+        .map_err(|_| anyhow!("Dekripcija sjednickog kljuca nije uspjela"))?;
+    out.truncate(length);
+    Ok(out)
+}
+
+#[derive(Debug)]
+pub enum UnsealError {
+    Malformed,
+    UnknownSender,
+    InvalidSignature,
+    DecryptionFailed(anyhow::Error),
+}
+
+impl fmt::Display for UnsealError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnsealError::Malformed => write!(f, "Neispravan format digitalnog pecata"),
+            UnsealError::UnknownSender => {
+                write!(
+                    f,
+                    "Posiljatelj nije poznat (pecat nije potpisan odabranim kljucem)"
+                )
+            }
+            UnsealError::InvalidSignature => {
+                write!(
+                    f,
+                    "Potpis digitalnog pecata nije valjan (pecat je izmijenjen)"
+                )
+            }
+            UnsealError::DecryptionFailed(e) => {
+                write!(f, "Dekripcija digitalnog pecata nije uspjela | {:?}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UnsealError {}
+
+// digitalni_pecat = { omotnica; E[Q(omotnica), SA] }
+// [otisak javnog kljuca posiljatelja][duljina omotnice (u32 BE)][omotnica][potpis (RSA-PSS)]
+pub struct DigitalSeal;
+
+impl DigitalSeal {
+    pub fn create(
+        data: &[u8],
+        recipient: &Rsa<Public>,
+        sender: &EncryptRsa,
+        label: &[u8],
+    ) -> Result<Vec<u8>> {
+        let envelope = DigitalEnvelope::create(data, recipient, label)?;
+        let sender_key = PKey::from_rsa(sender.rsa.clone())?;
+        let signature = sign_with_algorithm(&sender_key, SignatureAlgorithm::RsaPss, &envelope)?;
+
+        let mut out = Vec::with_capacity(FINGERPRINT_LENGTH + 4 + envelope.len() + signature.len());
+        out.extend_from_slice(&key_fingerprint(&sender_key)?);
+        write_length_prefixed(&mut out, &envelope);
+        out.extend_from_slice(&signature);
+        Ok(out)
+    }
+
+    pub fn open(
+        seal: &[u8],
+        recipient: &EncryptRsa,
+        senders: &[Rsa<Public>],
+    ) -> Result<Vec<u8>, UnsealError> {
+        if seal.len() < FINGERPRINT_LENGTH {
+            return Err(UnsealError::Malformed);
+        }
+        let (fingerprint, rest) = seal.split_at(FINGERPRINT_LENGTH);
+        let (envelope, signature) = read_length_prefixed(rest).ok_or(UnsealError::Malformed)?;
+
+        let sender_key = senders
+            .iter()
+            .filter_map(|sender| PKey::from_rsa(sender.clone()).ok())
+            .find(|sender| {
+                key_fingerprint(sender)
+                    .map(|f| f == fingerprint)
+                    .unwrap_or(false)
+            })
+            .ok_or(UnsealError::UnknownSender)?;
+
+        match verify_with_algorithm(&sender_key, SignatureAlgorithm::RsaPss, envelope, signature) {
+            Ok(true) => (),
+            _ => return Err(UnsealError::InvalidSignature),
+        }
+
+        DigitalEnvelope::open(envelope, recipient).map_err(UnsealError::DecryptionFailed)
+    }
+
+    pub fn create_file(
+        filename: &str,
+        recipient: &Rsa<Public>,
+        sender: &EncryptRsa,
+        label: &[u8],
+    ) -> Result<Vec<u8>> {
+        let data = read_file_to_buffer(filename)?;
+        DigitalSeal::create(&data, recipient, sender, label)
+    }
+
+    pub fn open_file(
+        filename: &str,
+        recipient: &EncryptRsa,
+        senders: &[Rsa<Public>],
+    ) -> Result<Vec<u8>> {
+        let seal = read_file_to_buffer(filename)?;
+        Ok(DigitalSeal::open(&seal, recipient, senders)?)
     }
 }
 
@@ -235,9 +813,157 @@ impl ShaHash {
         hasher.update(data);
         Ok(hasher.finish().to_vec())
     }
+}
 
-    pub fn hash_file(filename: &str) -> Result<Vec<u8>> {
-        let data = read_file_to_buffer(filename)?;
-        ShaHash::hash(&data)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hashing::HashAlgorithm, test_util::TempDir};
+    use std::{fs, path::Path};
+
+    #[test]
+    fn streaming_handles_files_larger_than_buffer() {
+        let dir = TempDir::new("tok");
+        let (data_path, encrypted_path, decrypted_path) = (
+            dir.path("datoteka"),
+            dir.path("kriptirano"),
+            dir.path("dekriptirano"),
+        );
+
+        // Nekoliko punih meduspremnika i nepotpuni zadnji dio
+        let data: Vec<u8> = (0..STREAM_BUFFER_SIZE * 5 + 123)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        fs::write(&data_path, &data).unwrap();
+
+        let key = Keygen::default().generate_256bit_key().to_vec();
+        let ciphers = [
+            EncryptAes::new(Cipher::aes_256_cbc(), key.clone(), None),
+            EncryptAes::new_aead(AeadAlgorithm::Aes256Gcm, key.clone()),
+            EncryptAes::new_aead(AeadAlgorithm::ChaCha20Poly1305, key),
+        ];
+        for aes in ciphers {
+            aes.encrypt_file(&data_path, &encrypted_path).unwrap();
+            // Zapis je isti kao kod obrade u memoriji
+            let container = Container::from_bytes(&fs::read(&encrypted_path).unwrap()).unwrap();
+            assert_eq!(aes.decrypt_container(&container).unwrap(), data);
+
+            aes.decrypt_file(&encrypted_path, &decrypted_path).unwrap();
+            assert_eq!(fs::read(&decrypted_path).unwrap(), data);
+        }
+
+        let rsa = EncryptRsa::new(Rsa::generate(2048).unwrap());
+        rsa.public_encrypt_file(&data_path, &encrypted_path)
+            .unwrap();
+        rsa.private_decrypt_file(&encrypted_path, &decrypted_path)
+            .unwrap();
+        assert_eq!(fs::read(&decrypted_path).unwrap(), data);
+
+        // Izmijenjeni podaci se ne dekriptiraju i ne ostaju na disku
+        let mut encrypted = fs::read(&encrypted_path).unwrap();
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 1;
+        fs::write(&encrypted_path, &encrypted).unwrap();
+        fs::remove_file(&decrypted_path).unwrap();
+        assert!(rsa
+            .private_decrypt_file(&encrypted_path, &decrypted_path)
+            .is_err());
+        assert!(!Path::new(&decrypted_path).exists());
+
+        for algorithm in HashAlgorithm::ALL {
+            assert_eq!(
+                algorithm.hash_file(&data_path).unwrap(),
+                algorithm.hash(&data).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn oaep_label_is_kept_in_envelope() {
+        let rsa = Rsa::generate(2048).unwrap();
+        let recipient = Rsa::public_key_from_der(&rsa.public_key_to_der().unwrap()).unwrap();
+        let recipient_key = EncryptRsa::new(rsa);
+        let data = b"Poruka s oznakom";
+
+        let envelope = DigitalEnvelope::create(data, &recipient, b"racun 2022/11").unwrap();
+        let container = Container::from_bytes(&envelope).unwrap();
+        assert_eq!(container.oaep_label, b"racun 2022/11");
+        assert_eq!(
+            DigitalEnvelope::open(&envelope, &recipient_key).unwrap(),
+            data
+        );
+
+        // Sjednicki kljuc je vezan uz oznaku
+        let mut changed = container;
+        changed.oaep_label = b"racun 2022/12".to_vec();
+        assert!(DigitalEnvelope::open(&changed.to_bytes(), &recipient_key).is_err());
+    }
+
+    #[test]
+    fn password_encrypted_files_need_only_the_password() {
+        let dir = TempDir::new("lozinka_aes");
+        let (data_path, encrypted_path, decrypted_path) = (
+            dir.path("datoteka"),
+            dir.path("kriptirano"),
+            dir.path("dekriptirano"),
+        );
+        let data = b"Poruka zasticena lozinkom".repeat(1000);
+        fs::write(&data_path, &data).unwrap();
+
+        // Slabi parametri da test bude brz
+        let kdfs = [
+            Kdf::Pbkdf2Sha256 { iterations: 1000 },
+            Kdf::Scrypt {
+                log_n: 10,
+                r: 8,
+                p: 1,
+            },
+            Kdf::Argon2id {
+                memory_kib: 1024,
+                iterations: 1,
+                parallelism: 1,
+            },
+        ];
+        for (kdf, algorithm) in kdfs.into_iter().zip(AeadAlgorithm::ALL.into_iter().cycle()) {
+            EncryptAes::encrypt_file_with_password(
+                algorithm,
+                kdf,
+                "tajna lozinka",
+                &data_path,
+                &encrypted_path,
+            )
+            .unwrap();
+            let container = Container::from_bytes(&fs::read(&encrypted_path).unwrap()).unwrap();
+            assert_eq!(container.key_wrap, KeyWrap::Password);
+            assert_eq!(
+                KdfParams::from_bytes(&container.kdf_params).unwrap().kdf,
+                kdf
+            );
+
+            EncryptAes::decrypt_file_with_password(
+                "tajna lozinka",
+                &encrypted_path,
+                &decrypted_path,
+            )
+            .unwrap();
+            assert_eq!(fs::read(&decrypted_path).unwrap(), data);
+            assert!(EncryptAes::decrypt_file_with_password(
+                "kriva lozinka",
+                &encrypted_path,
+                &decrypted_path
+            )
+            .is_err());
+        }
+
+        // Parametri iz datoteke koji bi zauzeli previse memorije se odbijaju
+        let huge = KdfParams {
+            kdf: Kdf::Argon2id {
+                memory_kib: u32::MAX,
+                iterations: 1,
+                parallelism: 1,
+            },
+            salt: vec![0; 16],
+        };
+        assert!(KdfParams::from_bytes(&huge.to_bytes()).is_err());
     }
 }
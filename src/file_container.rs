@@ -0,0 +1,276 @@
+use std::{fmt, io::Read};
+
+use anyhow::{anyhow, Result};
+
+use crate::{fingerprint::short_key_id, kdf::KdfParams};
+
+const MAGIC: &[u8; 4] = b"OS2K";
+// Polja zaglavlja su kratka, veca duljina znaci neispravan spremnik
+const MAX_FIELD_LENGTH: usize = 64 * 1024;
+// Verzija 2 dodaje parametre za izvodenje kljuca iz lozinke i oznaku RSA-OAEP
+pub const CURRENT_VERSION: u8 = 2;
+// Datoteke bez zaglavlja (prije uvodenja spremnika)
+pub const LEGACY_VERSION: u8 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerCipher {
+    Aes256Cbc,
+    Aes256Gcm,
+    ChaCha20Poly1305,
+    RsaPkcs1,
+}
+
+impl ContainerCipher {
+    fn id(&self) -> u8 {
+        match self {
+            ContainerCipher::Aes256Cbc => 1,
+            ContainerCipher::Aes256Gcm => 2,
+            ContainerCipher::ChaCha20Poly1305 => 3,
+            ContainerCipher::RsaPkcs1 => 4,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self> {
+        match id {
+            1 => Ok(ContainerCipher::Aes256Cbc),
+            2 => Ok(ContainerCipher::Aes256Gcm),
+            3 => Ok(ContainerCipher::ChaCha20Poly1305),
+            4 => Ok(ContainerCipher::RsaPkcs1),
+            _ => Err(anyhow!("Nepoznat algoritam u spremniku ({})", id)),
+        }
+    }
+}
+
+impl fmt::Display for ContainerCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContainerCipher::Aes256Cbc => write!(f, "AES-256-CBC"),
+            ContainerCipher::Aes256Gcm => write!(f, "AES-256-GCM"),
+            ContainerCipher::ChaCha20Poly1305 => write!(f, "ChaCha20-Poly1305"),
+            ContainerCipher::RsaPkcs1 => write!(f, "RSA (PKCS#1 v1.5)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyWrap {
+    None,
+    RsaPkcs1,
+    RsaOaepSha256,
+    Password,
+}
+
+impl KeyWrap {
+    fn id(&self) -> u8 {
+        match self {
+            KeyWrap::None => 0,
+            KeyWrap::RsaPkcs1 => 1,
+            KeyWrap::RsaOaepSha256 => 2,
+            KeyWrap::Password => 3,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self> {
+        match id {
+            0 => Ok(KeyWrap::None),
+            1 => Ok(KeyWrap::RsaPkcs1),
+            2 => Ok(KeyWrap::RsaOaepSha256),
+            3 => Ok(KeyWrap::Password),
+            _ => Err(anyhow!(
+                "Nepoznat nacin zastite kljuca u spremniku ({})",
+                id
+            )),
+        }
+    }
+}
+
+impl fmt::Display for KeyWrap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyWrap::None => write!(f, "bez zastite"),
+            KeyWrap::RsaPkcs1 => write!(f, "RSA (PKCS#1 v1.5)"),
+            KeyWrap::RsaOaepSha256 => write!(f, "RSA-OAEP (SHA-256)"),
+            KeyWrap::Password => write!(f, "lozinka"),
+        }
+    }
+}
+
+// [magic][verzija][algoritam][zastita kljuca][nonce][zasticeni kljuc][otisak kljuca]
+// [parametri izvodenja kljuca][oznaka RSA-OAEP][oznaka autentifikacije][podaci]
+// Polja izmedu zaglavlja i podataka imaju prefiks duljine (u32 BE).
+// Verzija 1 nema polja s parametrima izvodenja kljuca i oznakom RSA-OAEP.
+pub struct Container {
+    pub version: u8,
+    pub cipher: ContainerCipher,
+    pub key_wrap: KeyWrap,
+    pub nonce: Vec<u8>,
+    pub wrapped_key: Option<Vec<u8>>,
+    pub key_fingerprint: Vec<u8>,
+    pub kdf_params: Vec<u8>,
+    pub oaep_label: Vec<u8>,
+    pub tag: Vec<u8>,
+    pub payload: Vec<u8>,
+}
+
+impl Container {
+    pub fn new(cipher: ContainerCipher, key_wrap: KeyWrap) -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            cipher,
+            key_wrap,
+            nonce: Vec::new(),
+            wrapped_key: None,
+            key_fingerprint: Vec::new(),
+            kdf_params: Vec::new(),
+            oaep_label: Vec::new(),
+            tag: Vec::new(),
+            payload: Vec::new(),
+        }
+    }
+
+    pub fn is_container(data: &[u8]) -> bool {
+        data.starts_with(MAGIC)
+    }
+
+    // Zaglavlje se koristi i kao dodatni autentificirani podaci (AAD) kod AEAD algoritama
+    pub fn header(&self) -> Vec<u8> {
+        let mut header = MAGIC.to_vec();
+        header.push(self.version);
+        header.push(self.cipher.id());
+        header.push(self.key_wrap.id());
+        header
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.fields_to_bytes();
+        out.extend_from_slice(&self.payload);
+        out
+    }
+
+    // Sve osim podataka, kod obrade u dijelovima podaci se pisu odmah iza
+    pub fn fields_to_bytes(&self) -> Vec<u8> {
+        let mut out = self.header();
+        write_length_prefixed(&mut out, &self.nonce);
+        write_length_prefixed(&mut out, self.wrapped_key.as_deref().unwrap_or_default());
+        write_length_prefixed(&mut out, &self.key_fingerprint);
+        if self.version >= 2 {
+            write_length_prefixed(&mut out, &self.kdf_params);
+            write_length_prefixed(&mut out, &self.oaep_label);
+        }
+        write_length_prefixed(&mut out, &self.tag);
+        out
+    }
+
+    // Oznaka je poznata tek nakon enkripcije pa se naknadno upisuje na ovaj polozaj
+    pub fn tag_offset(&self) -> u64 {
+        let fields_length = self.fields_to_bytes().len();
+        (fields_length - self.tag.len()) as u64
+    }
+
+    // Cita samo zaglavlje i polja, ostatak citaca su podaci.
+    // Podaci bez zaglavlja se citaju kao stari format zadanog algoritma,
+    // a vec procitani pocetak podataka ostaje u `payload`.
+    pub fn read_from<R: Read>(reader: &mut R, legacy_cipher: ContainerCipher) -> Result<Self> {
+        let mut magic = Vec::with_capacity(MAGIC.len());
+        reader
+            .by_ref()
+            .take(MAGIC.len() as u64)
+            .read_to_end(&mut magic)?;
+        if magic != MAGIC {
+            let mut container = Container::new(legacy_cipher, KeyWrap::None);
+            container.version = LEGACY_VERSION;
+            container.payload = magic;
+            return Ok(container);
+        }
+
+        let malformed = || anyhow!("Neispravan format spremnika");
+        let mut header = [0; 3];
+        reader.read_exact(&mut header).map_err(|_| malformed())?;
+        let version = header[0];
+        if !(1..=CURRENT_VERSION).contains(&version) {
+            return Err(anyhow!("Nepodrzana verzija spremnika ({})", version));
+        }
+        let mut container = Container::new(
+            ContainerCipher::from_id(header[1])?,
+            KeyWrap::from_id(header[2])?,
+        );
+        container.version = version;
+        container.nonce = read_length_prefixed_from(reader).ok_or_else(malformed)?;
+        let wrapped_key = read_length_prefixed_from(reader).ok_or_else(malformed)?;
+        if !wrapped_key.is_empty() {
+            container.wrapped_key = Some(wrapped_key);
+        }
+        container.key_fingerprint = read_length_prefixed_from(reader).ok_or_else(malformed)?;
+        if version >= 2 {
+            container.kdf_params = read_length_prefixed_from(reader).ok_or_else(malformed)?;
+            container.oaep_label = read_length_prefixed_from(reader).ok_or_else(malformed)?;
+        }
+        container.tag = read_length_prefixed_from(reader).ok_or_else(malformed)?;
+        Ok(container)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if !Container::is_container(data) {
+            return Err(anyhow!("Datoteka nije ispravan spremnik"));
+        }
+        let mut reader = data;
+        let mut container = Container::read_from(&mut reader, ContainerCipher::Aes256Cbc)?;
+        container.payload = reader.to_vec();
+        Ok(container)
+    }
+}
+
+impl fmt::Display for Container {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.version == LEGACY_VERSION {
+            return write!(f, "Stari format bez zaglavlja ({})", self.cipher);
+        }
+        write!(f, "Spremnik v{}: {}", self.version, self.cipher)?;
+        if self.key_wrap != KeyWrap::None {
+            write!(f, ", kljuc zasticen: {}", self.key_wrap)?;
+        }
+        if let Ok(params) = KdfParams::from_bytes(&self.kdf_params) {
+            write!(f, " ({})", params.kdf)?;
+        }
+        if !self.oaep_label.is_empty() {
+            write!(
+                f,
+                ", oznaka: \"{}\"",
+                String::from_utf8_lossy(&self.oaep_label)
+            )?;
+        }
+        if !self.key_fingerprint.is_empty() {
+            write!(f, ", ID kljuca {}", short_key_id(&self.key_fingerprint))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn write_length_prefixed(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+pub fn read_length_prefixed(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    if data.len() < 4 {
+        return None;
+    }
+    let (length, rest) = data.split_at(4);
+    let length = u32::from_be_bytes(length.try_into().ok()?) as usize;
+    if rest.len() < length {
+        return None;
+    }
+    Some(rest.split_at(length))
+}
+
+fn read_length_prefixed_from<R: Read>(reader: &mut R) -> Option<Vec<u8>> {
+    let mut length = [0; 4];
+    reader.read_exact(&mut length).ok()?;
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FIELD_LENGTH {
+        return None;
+    }
+    let mut out = vec![0; length];
+    reader.read_exact(&mut out).ok()?;
+    Some(out)
+}
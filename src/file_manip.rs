@@ -1,12 +1,14 @@
 use std::{
-    fs::File,
-    io::{Read, Write},
+    fs::{remove_file, File},
+    io::{ErrorKind, Read, Write},
 };
 
 use anyhow::Result;
 
 const LINE_FEED: &[u8; 1] = b"\n";
 const CARRIGE_RETURN: &[u8; 1] = b"\r";
+// Velike datoteke se obraduju u dijelovima ove velicine
+pub const STREAM_BUFFER_SIZE: usize = 64 * 1024;
 
 pub fn write_file(filename: &str, contents: &[u8], append_to: bool) -> Result<()> {
     let mut file = if append_to {
@@ -22,17 +24,99 @@ pub fn read_file_to_buffer(filename: &str) -> Result<Vec<u8>> {
     let mut file = File::open(filename)?;
     let mut contents = Vec::new();
     file.read_to_end(&mut contents)?;
-    let last_byte_index = contents.len() - 1;
-    // CR LF
-    if contents[last_byte_index - 1] == CARRIGE_RETURN[0]
-        && contents[last_byte_index] == LINE_FEED[0]
-    {
-        contents.remove(last_byte_index);
-        contents.remove(last_byte_index - 1);
-    } else if contents[last_byte_index] == LINE_FEED[0] { // LF
-        contents.remove(last_byte_index);
-    } else if contents[last_byte_index] == CARRIGE_RETURN[0] { // CR
-        contents.remove(last_byte_index);
-    }
     Ok(contents)
 }
+
+// Samo na zahtjev korisnika, kljucevi, potpisi i kriptirani podaci se uvijek citaju binarno
+pub fn read_text_file(filename: &str) -> Result<Vec<u8>> {
+    Ok(normalize_line_endings(&read_file_to_buffer(filename)?))
+}
+
+// CR LF i CR se pretvaraju u LF, a zavrsni prijelom retka se uklanja
+pub fn normalize_line_endings(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut bytes = data.iter().peekable();
+    while let Some(&byte) = bytes.next() {
+        if byte == CARRIGE_RETURN[0] {
+            bytes.next_if_eq(&&LINE_FEED[0]);
+            out.push(LINE_FEED[0]);
+        } else {
+            out.push(byte);
+        }
+    }
+    if out.last() == Some(&LINE_FEED[0]) {
+        out.pop();
+    }
+    out
+}
+
+// Razmaci i tabulatori na kraju redaka, ocekuje se tekst s normaliziranim zavrsecima redaka
+pub fn trim_trailing_whitespace(text: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    for (i, line) in text.split(|&byte| byte == LINE_FEED[0]).enumerate() {
+        if i > 0 {
+            out.push(LINE_FEED[0]);
+        }
+        let end = line
+            .iter()
+            .rposition(|&byte| byte != b' ' && byte != b'\t')
+            .map_or(0, |i| i + 1);
+        out.extend_from_slice(&line[..end]);
+    }
+    out
+}
+
+pub fn read_chunks<R: Read>(reader: &mut R, mut f: impl FnMut(&[u8]) -> Result<()>) -> Result<()> {
+    let mut buffer = vec![0; STREAM_BUFFER_SIZE];
+    loop {
+        let length = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(length) => length,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        f(&buffer[..length])?;
+    }
+}
+
+// Ako obrada ne uspije, djelomicno zapisana datoteka se brise
+pub fn write_file_streamed(filename: &str, f: impl FnOnce(&mut File) -> Result<()>) -> Result<()> {
+    let mut file = File::create(filename)?;
+    let result = f(&mut file).and_then(|_| Ok(file.flush()?));
+    if result.is_err() {
+        drop(file);
+        let _ = remove_file(filename);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn binary_files_are_read_unchanged() {
+        let dir = TempDir::new("datoteke");
+        for data in [&b""[..], b"\n", b"\r", b"kljuc\r\n", b"\x00\x0d"] {
+            fs::write(dir.path("datoteka"), data).unwrap();
+            assert_eq!(read_file_to_buffer(&dir.path("datoteka")).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn text_files_are_read_with_normalized_line_endings() {
+        let dir = TempDir::new("datoteke_tekst");
+        fs::write(dir.path("tekst"), b"prvi\r\ndrugi\rtreci\n").unwrap();
+        assert_eq!(
+            read_text_file(&dir.path("tekst")).unwrap(),
+            b"prvi\ndrugi\ntreci"
+        );
+        assert_eq!(normalize_line_endings(b""), b"");
+        assert_eq!(
+            trim_trailing_whitespace(b"prvi \t\n  drugi\n \ntreci "),
+            b"prvi\n  drugi\n\ntreci"
+        );
+    }
+}
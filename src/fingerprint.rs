@@ -0,0 +1,190 @@
+use anyhow::Result;
+use openssl::pkey::{HasPublic, PKey};
+
+use crate::{encryption::ShaHash, signing::KeyAlgorithm};
+
+const SHORT_KEY_ID_LENGTH: usize = 8;
+// Velicina polja i znakovi kao kod `ssh-keygen -lv`
+const RANDOMART_WIDTH: usize = 17;
+const RANDOMART_HEIGHT: usize = 9;
+const RANDOMART_SYMBOLS: &[u8] = b" .o+=*BOX@%&#/^SE";
+
+// SHA-256 javnog kljuca u DER zapisu (SubjectPublicKeyInfo)
+pub fn key_fingerprint<T: HasPublic>(pkey: &PKey<T>) -> Result<Vec<u8>> {
+    ShaHash::hash(&pkey.public_key_to_der()?)
+}
+
+// Prvih 8 bajta otiska, pocetak zapisa otiska
+pub fn short_key_id(fingerprint: &[u8]) -> String {
+    hex::encode_upper(&fingerprint[..fingerprint.len().min(SHORT_KEY_ID_LENGTH)])
+}
+
+// Heksadekadski zapis u skupinama od 4 znaka
+pub fn format_fingerprint(fingerprint: &[u8]) -> String {
+    hex::encode_upper(fingerprint)
+        .as_bytes()
+        .chunks(4)
+        .map(|group| String::from_utf8_lossy(group).to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// "RSA 2048" ili "Ed25519", naslov slike otiska
+pub fn key_title<T: HasPublic>(pkey: &PKey<T>) -> Result<String> {
+    Ok(match KeyAlgorithm::of(pkey)? {
+        KeyAlgorithm::Rsa => format!("RSA {}", pkey.bits()),
+        algorithm => algorithm.to_string(),
+    })
+}
+
+// Slika otiska ("drunken bishop", isti algoritam kao u OpenSSH-u):
+// lovac krece iz sredine polja i svaka dva bita otiska ga pomicu dijagonalno,
+// a znak polja ovisi o tome koliko je puta lovac prosao kroz njega
+pub fn randomart(fingerprint: &[u8], title: &str) -> Vec<String> {
+    let mut field = [[0usize; RANDOMART_WIDTH]; RANDOMART_HEIGHT];
+    let start = (RANDOMART_WIDTH / 2, RANDOMART_HEIGHT / 2);
+    let (mut x, mut y) = start;
+    let last_symbol = RANDOMART_SYMBOLS.len() - 1;
+
+    for &byte in fingerprint {
+        let mut input = byte;
+        for _ in 0..4 {
+            x = if input & 1 != 0 {
+                (x + 1).min(RANDOMART_WIDTH - 1)
+            } else {
+                x.saturating_sub(1)
+            };
+            y = if input & 2 != 0 {
+                (y + 1).min(RANDOMART_HEIGHT - 1)
+            } else {
+                y.saturating_sub(1)
+            };
+            if field[y][x] < last_symbol - 2 {
+                field[y][x] += 1;
+            }
+            input >>= 2;
+        }
+    }
+    field[start.1][start.0] = last_symbol - 1;
+    field[y][x] = last_symbol;
+
+    let mut lines = vec![randomart_border(title)];
+    for row in field {
+        let row: String = row
+            .iter()
+            .map(|&count| RANDOMART_SYMBOLS[count] as char)
+            .collect();
+        lines.push(format!("|{}|", row));
+    }
+    lines.push(randomart_border("SHA256"));
+    lines
+}
+
+fn randomart_border(label: &str) -> String {
+    let label: String = format!("[{}]", label)
+        .chars()
+        .take(RANDOMART_WIDTH)
+        .collect();
+    let left = (RANDOMART_WIDTH - label.len()) / 2;
+    let right = RANDOMART_WIDTH - label.len() - left;
+    format!("+{}{}{}+", "-".repeat(left), label, "-".repeat(right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        key_format::{export_public_key, KeyFormat},
+        test_util::TempDir,
+    };
+    use openssl::{
+        base64::decode_block,
+        pkey::{Id, Private},
+        rsa::Rsa,
+    };
+    use std::{fs, process::Command};
+
+    fn keys() -> [PKey<Private>; 2] {
+        [
+            PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap(),
+            PKey::generate_ed25519().unwrap(),
+        ]
+    }
+
+    fn openssh_key(pkey: &PKey<Private>) -> String {
+        String::from_utf8(export_public_key(pkey, KeyFormat::OpenSsh).unwrap()).unwrap()
+    }
+
+    // Slika se crta iz SHA-256 otiska OpenSSH zapisa kljuca
+    fn openssh_randomart(pkey: &PKey<Private>) -> Vec<String> {
+        let blob = decode_block(openssh_key(pkey).split_whitespace().nth(1).unwrap()).unwrap();
+        randomart(&openssl::sha::sha256(&blob), &key_title(pkey).unwrap())
+    }
+
+    #[test]
+    fn key_ids_are_part_of_fingerprints_and_exported_keys() {
+        for pkey in keys() {
+            let fingerprint = key_fingerprint(&pkey).unwrap();
+            let key_id = short_key_id(&fingerprint);
+            assert_eq!(key_id.len(), 2 * SHORT_KEY_ID_LENGTH);
+            assert!(format_fingerprint(&fingerprint)
+                .replace(' ', "")
+                .starts_with(&key_id));
+
+            let jwk = export_public_key(&pkey, KeyFormat::Jwk).unwrap();
+            let jwk: serde_json::Value = serde_json::from_slice(&jwk).unwrap();
+            assert_eq!(jwk["kid"], key_id.as_str());
+            assert!(openssh_key(&pkey).trim_end().ends_with(&key_id));
+        }
+    }
+
+    #[test]
+    fn randomart_matches_ssh_keygen_vector() {
+        // Slika koju ssh-keygen -lv crta za Ed25519 kljuc iz sjemena 00 01 .. 1f
+        let seed: Vec<u8> = (0..32).collect();
+        let pkey = PKey::private_key_from_raw_bytes(&seed, Id::ED25519).unwrap();
+        assert!(openssh_key(&pkey).starts_with(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4"
+        ));
+        let expected = [
+            "|===+ +.  ..+=.o. |",
+            "|o.  + o o .*..   |",
+            "|o .  + o .B.     |",
+            "|.o  E o  =...    |",
+            "|  o ... S.oo .   |",
+            "|   o.+ . .o o    |",
+            "|   o. . .. . +   |",
+            "|    o . o   o .  |",
+            "|     oo+         |",
+            "+----[SHA256]-----+",
+        ];
+        let art = openssh_randomart(&pkey);
+        assert_eq!(art.len(), RANDOMART_HEIGHT + 2);
+        assert_eq!(art[1..], expected);
+    }
+
+    #[test]
+    #[ignore = "potreban je program ssh-keygen"]
+    fn randomart_matches_ssh_keygen() {
+        let dir = TempDir::new("fingerprint");
+        let ssh_path = dir.path("kljuc.pub");
+        for pkey in keys() {
+            fs::write(&ssh_path, openssh_key(&pkey)).unwrap();
+            let output = Command::new("ssh-keygen")
+                .arg("-lv")
+                .arg("-f")
+                .arg(&ssh_path)
+                .output()
+                .expect("ssh-keygen naredba nije dostupna");
+            assert!(output.status.success());
+            let output = String::from_utf8(output.stdout).unwrap();
+            let expected: Vec<&str> = output.lines().skip(1).collect();
+
+            let art = openssh_randomart(&pkey);
+            assert_eq!(art[1..], expected[1..]);
+            if pkey.id() == Id::RSA {
+                assert_eq!(art, expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use anyhow::Result;
+use iced::{
+    widget::{self, checkbox, pick_list, text, Row},
+    Element,
+};
+use tinyfiledialogs::{input_box, open_file_dialog};
+
+use crate::{
+    certificate::{
+        create_certificate_request, create_self_signed_certificate, describe_certificate,
+        subject_name, CertificateParams, ExtendedKeyUsage, KeyUsage, VALIDITY_DAYS,
+    },
+    file_manip::{read_file_to_buffer, write_file},
+    private_key::load_private_key,
+    CERTIFICATE_FILENAME, CERTIFICATE_REQUEST_FILENAME,
+};
+
+use super::{
+    private_key_passphrase,
+    styled_components::{styled_button, styled_column, styled_error, styled_row},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum CertificateMessage {
+    EditSubject,
+    SelectValidity(u32),
+    ToggleKeyUsage(KeyUsage, bool),
+    ToggleExtendedKeyUsage(ExtendedKeyUsage, bool),
+    CreateCertificate,
+    CreateRequest,
+    ShowCertificate,
+}
+
+pub struct CertificateView {
+    params: CertificateParams,
+    description: Option<String>,
+    error: Option<anyhow::Error>,
+}
+
+impl CertificateView {
+    pub fn new() -> Self {
+        Self {
+            params: CertificateParams::default(),
+            description: None,
+            error: None,
+        }
+    }
+
+    pub fn update(&mut self, message: CertificateMessage) {
+        self.error = None;
+        let result = match message {
+            CertificateMessage::EditSubject => self.edit_subject(),
+            CertificateMessage::SelectValidity(days) => {
+                self.params.validity_days = days;
+                Ok(())
+            }
+            CertificateMessage::ToggleKeyUsage(usage, enabled) => {
+                toggle(&mut self.params.key_usage, usage, enabled);
+                Ok(())
+            }
+            CertificateMessage::ToggleExtendedKeyUsage(usage, enabled) => {
+                toggle(&mut self.params.extended_key_usage, usage, enabled);
+                Ok(())
+            }
+            CertificateMessage::CreateCertificate => self.create(false),
+            CertificateMessage::CreateRequest => self.create(true),
+            CertificateMessage::ShowCertificate => self.show(),
+        };
+        if let Err(e) = result {
+            self.error = Some(e);
+        }
+    }
+
+    fn edit_subject(&mut self) -> Result<()> {
+        let subject = match input_box(
+            "Subjekt certifikata",
+            "Unesite subjekt (npr. CN=Ivan Horvat, O=FER, C=HR)",
+            &self.params.subject,
+        ) {
+            Some(subject) => subject,
+            None => return Ok(()),
+        };
+        subject_name(&subject)?;
+        self.params.subject = subject.trim().to_string();
+        Ok(())
+    }
+
+    // Certifikat i zahtjev se izdaju za par kljuceva aplikacije
+    fn create(&mut self, request: bool) -> Result<()> {
+        let pkey = load_private_key(None, private_key_passphrase(None)?.as_deref())?;
+        let (filename, pem) = if request {
+            let request = create_certificate_request(&pkey, &self.params)?;
+            (CERTIFICATE_REQUEST_FILENAME, request.to_pem()?)
+        } else {
+            let cert = create_self_signed_certificate(&pkey, &self.params)?;
+            (CERTIFICATE_FILENAME, cert.to_pem()?)
+        };
+        write_file(filename, &pem, false)?;
+        self.description = Some(format!(
+            "Spremljeno u datoteku {}\n{}",
+            filename,
+            describe_certificate(&pem)?
+        ));
+        Ok(())
+    }
+
+    fn show(&mut self) -> Result<()> {
+        let path = match open_file_dialog("Odabir certifikata ili zahtjeva", "", None) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        self.description = Some(describe_certificate(&read_file_to_buffer(&path)?)?);
+        Ok(())
+    }
+
+    pub fn view(&self) -> Element<'_, CertificateMessage> {
+        let mut column = styled_column(None).push(text("Certifikat (X.509)").size(30));
+
+        if let Some(e) = &self.error {
+            column = column.push(styled_error(e));
+        }
+
+        let subject = widget::column![
+            text(format!("Subjekt: {}", self.params.subject)),
+            styled_button("Uredi subjekt").on_press(CertificateMessage::EditSubject)
+        ]
+        .spacing(5);
+        let validity = widget::column![
+            text("Valjanost (dana)"),
+            pick_list(
+                &VALIDITY_DAYS[..],
+                Some(self.params.validity_days),
+                CertificateMessage::SelectValidity
+            )
+        ]
+        .spacing(5);
+
+        column = column
+            .push(styled_row().push(subject).push(validity))
+            .push(key_usage_row(
+                &self.params,
+                CertificateMessage::ToggleKeyUsage,
+                CertificateMessage::ToggleExtendedKeyUsage,
+            ))
+            .push(
+                styled_row()
+                    .push(
+                        styled_button("Kreiraj samopotpisani certifikat")
+                            .on_press(CertificateMessage::CreateCertificate),
+                    )
+                    .push(
+                        styled_button("Kreiraj zahtjev za certifikat")
+                            .on_press(CertificateMessage::CreateRequest),
+                    )
+                    .push(
+                        styled_button("Prikazi certifikat ili zahtjev")
+                            .on_press(CertificateMessage::ShowCertificate),
+                    ),
+            );
+
+        if let Some(description) = &self.description {
+            column = column.push(text(description));
+        }
+        column.into()
+    }
+}
+
+pub(super) fn key_usage_row<'a, Message: Clone + 'a>(
+    params: &CertificateParams,
+    on_key_usage: fn(KeyUsage, bool) -> Message,
+    on_extended_key_usage: fn(ExtendedKeyUsage, bool) -> Message,
+) -> Row<'a, Message> {
+    let mut key_usage = widget::column![text("Namjena kljuca")].spacing(5);
+    for usage in KeyUsage::ALL {
+        key_usage = key_usage.push(checkbox(
+            usage.to_string(),
+            params.key_usage.contains(&usage),
+            move |enabled| on_key_usage(usage, enabled),
+        ));
+    }
+    let mut extended_key_usage = widget::column![text("Prosirena namjena kljuca")].spacing(5);
+    for usage in ExtendedKeyUsage::ALL {
+        extended_key_usage = extended_key_usage.push(checkbox(
+            usage.to_string(),
+            params.extended_key_usage.contains(&usage),
+            move |enabled| on_extended_key_usage(usage, enabled),
+        ));
+    }
+    styled_row().push(key_usage).push(extended_key_usage)
+}
+
+pub(super) fn toggle<T: PartialEq>(values: &mut Vec<T>, value: T, enabled: bool) {
+    values.retain(|v| *v != value);
+    if enabled {
+        values.push(value);
+    }
+}
@@ -0,0 +1,405 @@
+use std::fmt;
+
+use anyhow::Result;
+use iced::{
+    widget::{self, checkbox, pick_list, text},
+    Element,
+};
+use tinyfiledialogs::{input_box, open_file_dialog};
+
+use crate::{
+    certificate::{
+        load_certificate_request, name_to_string, CertificateParams, ExtendedKeyUsage, KeyUsage,
+        VALIDITY_DAYS,
+    },
+    certificate_authority::{CertificateAuthority, INTERMEDIATE_CA_DIRECTORY, ROOT_CA_DIRECTORY},
+    crl::RevocationReason,
+    encryption::RsaKeyParams,
+    file_manip::{read_file_to_buffer, write_file},
+    signing::KeyAlgorithm,
+    trust_store::{TrustStore, TRUSTED_ROOTS_DIRECTORY},
+    ISSUED_CERTIFICATE_FILENAME,
+};
+
+use super::{
+    certificate::{key_usage_row, toggle},
+    keygen::prompt_new_passphrase,
+    private_key_passphrase,
+    styled_components::{styled_button, styled_column, styled_error, styled_row, RED},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaLevel {
+    Root,
+    Intermediate,
+}
+
+impl CaLevel {
+    const ALL: [CaLevel; 2] = [CaLevel::Root, CaLevel::Intermediate];
+
+    fn directory(&self) -> &'static str {
+        match self {
+            CaLevel::Root => ROOT_CA_DIRECTORY,
+            CaLevel::Intermediate => INTERMEDIATE_CA_DIRECTORY,
+        }
+    }
+
+    fn default_subject(&self) -> &'static str {
+        match self {
+            CaLevel::Root => "CN=OS2 Korijensko CA",
+            CaLevel::Intermediate => "CN=OS2 Podredeno CA",
+        }
+    }
+
+    // Podredeno tijelo mora isteci prije korijenskog
+    fn validity_days(&self) -> u32 {
+        match self {
+            CaLevel::Root => 3650,
+            CaLevel::Intermediate => 1825,
+        }
+    }
+}
+
+impl fmt::Display for CaLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                CaLevel::Root => "Korijensko tijelo",
+                CaLevel::Intermediate => "Podredeno tijelo",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CertificateAuthorityMessage {
+    SelectLevel(CaLevel),
+    SelectAlgorithm(KeyAlgorithm),
+    ProtectKey(bool),
+    SelectValidity(u32),
+    ToggleKeyUsage(KeyUsage, bool),
+    ToggleExtendedKeyUsage(ExtendedKeyUsage, bool),
+    CreateCa,
+    SignRequest,
+    TrustRoot,
+    SelectRevocationReason(RevocationReason),
+    Revoke,
+    CreateCrl,
+}
+
+pub struct CertificateAuthorityView {
+    level: CaLevel,
+    algorithm: KeyAlgorithm,
+    protect_key: bool,
+    params: CertificateParams,
+    revocation_reason: RevocationReason,
+    info: Option<String>,
+    error: Option<anyhow::Error>,
+}
+
+impl CertificateAuthorityView {
+    pub fn new() -> Self {
+        Self {
+            level: CaLevel::Root,
+            algorithm: KeyAlgorithm::Rsa,
+            protect_key: true,
+            params: CertificateParams::default(),
+            revocation_reason: RevocationReason::KeyCompromise,
+            info: None,
+            error: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.info = None;
+        self.error = None;
+    }
+
+    pub fn update(&mut self, message: CertificateAuthorityMessage) {
+        self.info = None;
+        self.error = None;
+        let result = match message {
+            CertificateAuthorityMessage::SelectLevel(level) => {
+                self.level = level;
+                Ok(())
+            }
+            CertificateAuthorityMessage::SelectAlgorithm(algorithm) => {
+                self.algorithm = algorithm;
+                Ok(())
+            }
+            CertificateAuthorityMessage::ProtectKey(protect) => {
+                self.protect_key = protect;
+                Ok(())
+            }
+            CertificateAuthorityMessage::SelectValidity(days) => {
+                self.params.validity_days = days;
+                Ok(())
+            }
+            CertificateAuthorityMessage::ToggleKeyUsage(usage, enabled) => {
+                toggle(&mut self.params.key_usage, usage, enabled);
+                Ok(())
+            }
+            CertificateAuthorityMessage::ToggleExtendedKeyUsage(usage, enabled) => {
+                toggle(&mut self.params.extended_key_usage, usage, enabled);
+                Ok(())
+            }
+            CertificateAuthorityMessage::CreateCa => self.create_ca(),
+            CertificateAuthorityMessage::SignRequest => self.sign_request(),
+            CertificateAuthorityMessage::TrustRoot => self.trust_root(),
+            CertificateAuthorityMessage::SelectRevocationReason(reason) => {
+                self.revocation_reason = reason;
+                Ok(())
+            }
+            CertificateAuthorityMessage::Revoke => self.revoke(),
+            CertificateAuthorityMessage::CreateCrl => self.create_crl(),
+        };
+        if let Err(e) = result {
+            self.error = Some(e);
+        }
+    }
+
+    fn create_ca(&mut self) -> Result<()> {
+        let subject = match input_box(
+            "Subjekt certifikacijskog tijela",
+            "Unesite subjekt (npr. CN=OS2 Korijensko CA, O=FER, C=HR)",
+            self.level.default_subject(),
+        ) {
+            Some(subject) => subject,
+            None => return Ok(()),
+        };
+        let params = CertificateParams {
+            subject,
+            validity_days: self.level.validity_days(),
+            ..CertificateParams::default()
+        };
+        let passphrase = if self.protect_key {
+            prompt_new_passphrase(false)?
+        } else {
+            None
+        };
+        let ca = match self.level {
+            CaLevel::Root => CertificateAuthority::create_root(
+                ROOT_CA_DIRECTORY,
+                &params,
+                self.algorithm,
+                RsaKeyParams::default(),
+                passphrase.as_deref(),
+            )?,
+            CaLevel::Intermediate => {
+                let root = CertificateAuthority::open(ROOT_CA_DIRECTORY)?;
+                let root_passphrase = private_key_passphrase(Some(&root.key_path()))?;
+                root.create_intermediate(
+                    INTERMEDIATE_CA_DIRECTORY,
+                    &params,
+                    self.algorithm,
+                    RsaKeyParams::default(),
+                    passphrase.as_deref(),
+                    root_passphrase.as_deref(),
+                )?
+            }
+        };
+        self.info = Some(format!(
+            "Certifikacijsko tijelo {} je kreirano u direktoriju {}",
+            name_to_string(ca.certificate().subject_name()),
+            self.level.directory()
+        ));
+        Ok(())
+    }
+
+    // Izdani certifikat se sprema zajedno s lancem certifikata tijela
+    fn sign_request(&mut self) -> Result<()> {
+        let path = match open_file_dialog("Odabir zahtjeva za certifikat", "", None) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let request = load_certificate_request(&read_file_to_buffer(&path)?)?;
+        let ca = CertificateAuthority::open(self.level.directory())?;
+        let ca_passphrase = private_key_passphrase(Some(&ca.key_path()))?;
+        let certificate = ca.sign_request(&request, &self.params, ca_passphrase.as_deref())?;
+        let mut pem = certificate.to_pem()?;
+        for ca_certificate in ca.chain()? {
+            pem.extend(ca_certificate.to_pem()?);
+        }
+        write_file(ISSUED_CERTIFICATE_FILENAME, &pem, false)?;
+        self.info = Some(format!(
+            "Certifikat za {} je spremljen u datoteku {}",
+            name_to_string(certificate.subject_name()),
+            ISSUED_CERTIFICATE_FILENAME
+        ));
+        Ok(())
+    }
+
+    // Potpisi ciji lanac vodi do pouzdanog korijenskog certifikata se mogu provjeriti bez javnog kljuca
+    fn trust_root(&mut self) -> Result<()> {
+        let ca = CertificateAuthority::open(ROOT_CA_DIRECTORY)?;
+        TrustStore::open(None)?.add_root(ca.certificate())?;
+        self.info = Some(format!(
+            "Certifikat {} je dodan u pouzdane korijenske certifikate (direktorij {})",
+            name_to_string(ca.certificate().subject_name()),
+            TRUSTED_ROOTS_DIRECTORY
+        ));
+        Ok(())
+    }
+
+    fn revoke(&mut self) -> Result<()> {
+        let serial_number = match input_box(
+            "Opoziv certifikata",
+            "Unesite serijski broj certifikata (npr. 1001)",
+            "",
+        ) {
+            Some(serial_number) => serial_number,
+            None => return Ok(()),
+        };
+        let entry = CertificateAuthority::open(self.level.directory())?
+            .revoke(&serial_number, self.revocation_reason)?;
+        self.info = Some(format!(
+            "Certifikat {} ({}) je opozvan. Opoziv vrijedi nakon izdavanja nove liste opozvanih certifikata.",
+            entry.serial_number, entry.subject
+        ));
+        Ok(())
+    }
+
+    fn create_crl(&mut self) -> Result<()> {
+        let ca = CertificateAuthority::open(self.level.directory())?;
+        let crl = ca.create_crl(private_key_passphrase(Some(&ca.key_path()))?.as_deref())?;
+        self.info = Some(format!(
+            "Lista opozvanih certifikata ({} opozvanih) je spremljena u datoteku {}",
+            crl.revoked_certificates()?.len(),
+            ca.crl_path()
+        ));
+        Ok(())
+    }
+
+    pub fn view(&self) -> Element<'_, CertificateAuthorityMessage> {
+        let mut column = styled_column(None);
+
+        if let Some(e) = &self.error {
+            column = column.push(styled_error(e));
+        }
+        if let Some(info) = &self.info {
+            column = column.push(text(info));
+        }
+
+        column = column.push(
+            styled_row()
+                .push(
+                    widget::column![
+                        text("Certifikacijsko tijelo"),
+                        pick_list(
+                            &CaLevel::ALL[..],
+                            Some(self.level),
+                            CertificateAuthorityMessage::SelectLevel
+                        )
+                    ]
+                    .spacing(5),
+                )
+                .push(
+                    widget::column![
+                        text("Algoritam kljuca tijela"),
+                        pick_list(
+                            &KeyAlgorithm::ALL[..],
+                            Some(self.algorithm),
+                            CertificateAuthorityMessage::SelectAlgorithm
+                        )
+                    ]
+                    .spacing(5),
+                )
+                .push(checkbox(
+                    "Zastiti kljuc tijela lozinkom",
+                    self.protect_key,
+                    CertificateAuthorityMessage::ProtectKey,
+                ))
+                .push(
+                    styled_button("Kreiraj certifikacijsko tijelo")
+                        .on_press(CertificateAuthorityMessage::CreateCa),
+                ),
+        );
+
+        let ca = match CertificateAuthority::open(self.level.directory()) {
+            Ok(ca) => ca,
+            Err(_) => {
+                return column
+                    .push(text(format!(
+                        "{} jos nije kreirano (direktorij {})",
+                        self.level,
+                        self.level.directory()
+                    )))
+                    .into();
+            }
+        };
+        column = column
+            .push(text(format!(
+                "{}: {}, vrijedi do {}",
+                self.level,
+                name_to_string(ca.certificate().subject_name()),
+                ca.certificate().not_after()
+            )))
+            .push(
+                styled_row()
+                    .push(
+                        widget::column![
+                            text("Valjanost izdanog certifikata (dana)"),
+                            pick_list(
+                                &VALIDITY_DAYS[..],
+                                Some(self.params.validity_days),
+                                CertificateAuthorityMessage::SelectValidity
+                            )
+                        ]
+                        .spacing(5),
+                    )
+                    .push(
+                        styled_button("Potpisi zahtjev za certifikat")
+                            .on_press(CertificateAuthorityMessage::SignRequest),
+                    )
+                    .push(if self.level == CaLevel::Root {
+                        styled_button("Dodaj u pouzdane korijenske certifikate")
+                            .on_press(CertificateAuthorityMessage::TrustRoot)
+                    } else {
+                        styled_button("Dodaj u pouzdane korijenske certifikate")
+                    }),
+            )
+            .push(key_usage_row(
+                &self.params,
+                CertificateAuthorityMessage::ToggleKeyUsage,
+                CertificateAuthorityMessage::ToggleExtendedKeyUsage,
+            ))
+            .push(
+                styled_row()
+                    .push(
+                        widget::column![
+                            text("Razlog opoziva"),
+                            pick_list(
+                                &RevocationReason::ALL[..],
+                                Some(self.revocation_reason),
+                                CertificateAuthorityMessage::SelectRevocationReason
+                            )
+                        ]
+                        .spacing(5),
+                    )
+                    .push(
+                        styled_button("Opozovi certifikat")
+                            .on_press(CertificateAuthorityMessage::Revoke),
+                    )
+                    .push(
+                        styled_button("Izdaj listu opozvanih certifikata (CRL)")
+                            .on_press(CertificateAuthorityMessage::CreateCrl),
+                    ),
+            );
+
+        match ca.issued_certificates() {
+            Ok(issued) if issued.is_empty() => {
+                column = column.push(text("Tijelo jos nije izdalo nijedan certifikat"));
+            }
+            Ok(issued) => {
+                let lines: Vec<String> = issued.iter().map(|entry| entry.to_string()).collect();
+                column = column.push(text(format!("Izdani certifikati:\n{}", lines.join("\n"))));
+            }
+            Err(e) => {
+                column = column.push(text(e.to_string()).style(RED));
+            }
+        }
+        column.into()
+    }
+}
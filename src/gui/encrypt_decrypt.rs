@@ -1,35 +1,60 @@
-use anyhow::anyhow;
+use std::fs::File;
+
+use anyhow::{anyhow, Result};
 use iced::{
-    widget::{self, text},
+    widget::{self, checkbox, pick_list, text},
     Element,
 };
-use openssl::symm::Cipher;
-use tinyfiledialogs::open_file_dialog;
+use tinyfiledialogs::{input_box, open_file_dialog, password_box};
 
 use crate::{
-    encryption::{EncryptRsa, EncryptAes},
-    file_manip::{read_file_to_buffer, write_file},
-    SECRET_KEY_FILENAME,
+    chunked_encryption::{check_chunk_size, is_chunked_file, ChunkedAead, ChunkedHeader},
+    encryption::{load_secret_key, AeadAlgorithm, EncryptAes, EncryptRsa, MIN_RSA_KEY_LENGTH},
+    file_container::{Container, ContainerCipher, LEGACY_VERSION},
+    file_manip::{write_file, write_file_streamed, STREAM_BUFFER_SIZE},
+    fingerprint::short_key_id,
+    kdf::{Kdf, KdfAlgorithm, KdfStrength},
 };
 
 use super::{
-    path_to_filename,
+    path_to_filename, private_key_passphrase,
     styled_components::{styled_button, styled_column, styled_error, styled_row},
 };
 
 #[derive(Debug, Clone, Copy)]
 pub enum EncryptDecryptMessage {
+    SelectAlgorithm(AeadAlgorithm),
+    SelectKdf(KdfAlgorithm),
+    SelectKdfStrength(KdfStrength),
+    AllowLegacy(bool),
+    ChunkedFormat(bool),
+    EditChunkSize,
+    EditOaepLabel,
     LoadKeys,
     LoadFile,
     EncryptSymmetric,
     EncryptAsymmetric,
     DecryptSymmetric,
     DecryptAsymmetric,
+    DecryptChunk,
+    DecryptRange,
+    EncryptPassword,
+    DecryptPassword,
 }
 
 pub struct EncryptDecryptView {
+    algorithm: AeadAlgorithm,
+    kdf_algorithm: KdfAlgorithm,
+    kdf_strength: KdfStrength,
+    allow_legacy: bool,
+    chunked_format: bool,
+    chunk_size: u32,
+    oaep_label: String,
+    min_rsa_key_length: u32,
     selected_file: Option<String>,
+    file_info: Option<String>,
     symmetric: Option<EncryptAes>,
+    chunked: Option<ChunkedAead>,
     asymmetric: Option<EncryptRsa>,
     error: Option<anyhow::Error>,
 }
@@ -37,126 +62,328 @@ pub struct EncryptDecryptView {
 impl EncryptDecryptView {
     pub fn new() -> Self {
         Self {
+            algorithm: AeadAlgorithm::Aes256Gcm,
+            kdf_algorithm: KdfAlgorithm::Argon2id,
+            kdf_strength: KdfStrength::Basic,
+            allow_legacy: false,
+            chunked_format: false,
+            chunk_size: STREAM_BUFFER_SIZE as u32,
+            oaep_label: String::new(),
+            min_rsa_key_length: MIN_RSA_KEY_LENGTH,
             selected_file: None,
+            file_info: None,
             symmetric: None,
+            chunked: None,
             asymmetric: None,
             error: None,
         }
     }
 
+    pub fn set_min_rsa_key_length(&mut self, bits: u32) {
+        self.min_rsa_key_length = bits;
+    }
+
     pub fn reset(&mut self) {
         self.selected_file = None;
+        self.file_info = None;
         self.symmetric = None;
+        self.chunked = None;
         self.asymmetric = None;
         self.error = None;
     }
 
-    fn do_write(&mut self, filename: &str, data: &[u8]) {
-        match write_file(filename, data, false) {
-            Ok(_) => (),
-            Err(e) => {
-                self.error = Some(e);
-            }
-        }
-    }
-
     pub fn update(&mut self, message: EncryptDecryptMessage) {
         self.error = None;
         match message {
+            EncryptDecryptMessage::SelectAlgorithm(algorithm) => {
+                self.algorithm = algorithm;
+                self.symmetric = None;
+                self.chunked = None;
+            }
+            EncryptDecryptMessage::SelectKdf(algorithm) => {
+                self.kdf_algorithm = algorithm;
+            }
+            EncryptDecryptMessage::SelectKdfStrength(strength) => {
+                self.kdf_strength = strength;
+            }
+            EncryptDecryptMessage::ChunkedFormat(chunked) => {
+                self.chunked_format = chunked;
+            }
+            EncryptDecryptMessage::EditChunkSize => {
+                let chunk_size = match prompt_number(
+                    "Velicina dijela",
+                    "Unesite velicinu dijela u bajtovima",
+                    self.chunk_size as u64,
+                ) {
+                    Ok(size) => size,
+                    Err(e) => {
+                        self.error = Some(e);
+                        return;
+                    }
+                };
+                let res = u32::try_from(chunk_size)
+                    .map_err(|_| anyhow!("Velicina dijela je prevelika"))
+                    .and_then(|size| check_chunk_size(size).map(|_| size));
+                match res {
+                    Ok(size) => {
+                        self.chunk_size = size;
+                        if let Some(chunked) = self.chunked.as_mut() {
+                            if let Err(e) = chunked.set_chunk_size(size) {
+                                self.error = Some(e);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        self.error = Some(e);
+                    }
+                }
+            }
+            EncryptDecryptMessage::AllowLegacy(allow) => {
+                self.allow_legacy = allow;
+                if let Some(asymmetric) = self.asymmetric.as_mut() {
+                    asymmetric.set_allow_legacy(allow);
+                }
+            }
+            EncryptDecryptMessage::EditOaepLabel => {
+                if let Some(label) = input_box(
+                    "Oznaka RSA-OAEP",
+                    "Unesite oznaku (moze biti prazna)",
+                    &self.oaep_label,
+                ) {
+                    self.oaep_label = label;
+                    if let Some(asymmetric) = self.asymmetric.as_mut() {
+                        asymmetric.set_oaep_label(self.oaep_label.as_bytes());
+                    }
+                }
+            }
             EncryptDecryptMessage::LoadKeys => {
-                let asymetric = match EncryptRsa::from_files(None) {
+                let asymetric = private_key_passphrase(None).and_then(|passphrase| {
+                    EncryptRsa::from_files(None, passphrase.as_deref(), self.min_rsa_key_length)
+                });
+                let mut asymetric = match asymetric {
                     Ok(rsa) => rsa,
                     Err(e) => {
                         self.error = Some(e);
                         return;
                     }
                 };
+                asymetric.set_allow_legacy(self.allow_legacy);
+                asymetric.set_oaep_label(self.oaep_label.as_bytes());
                 self.asymmetric = Some(asymetric);
-                let secret_key = match read_file_to_buffer(SECRET_KEY_FILENAME) {
+                let secret_key = match load_secret_key(None) {
                     Ok(key) => key,
                     Err(e) => {
-                        self.error =
-                            Some(anyhow!("Ucitavanje tajnog kljuca nije uspijelo | {:?}", e));
+                        self.error = Some(e);
                         return;
                     }
                 };
-                let cipher = Cipher::aes_256_cbc();
-                self.symmetric = Some(EncryptAes::new(cipher, secret_key, None));
+                let mut chunked = ChunkedAead::new(self.algorithm, secret_key.clone());
+                if let Err(e) = chunked.set_chunk_size(self.chunk_size) {
+                    self.error = Some(e);
+                    return;
+                }
+                self.chunked = Some(chunked);
+                self.symmetric = Some(EncryptAes::new_aead(self.algorithm, secret_key));
             }
             EncryptDecryptMessage::LoadFile => {
                 self.selected_file = open_file_dialog("Odabir datoteke", "", None);
+                self.file_info = self.selected_file.as_deref().and_then(file_info);
             }
             EncryptDecryptMessage::EncryptAsymmetric => {
                 if let (Some(encrypt), Some(path)) =
                     (self.asymmetric.as_ref(), self.selected_file.as_ref())
                 {
-                    let res = match encrypt.public_encrypt_file(path) {
-                        Ok(r) => r,
+                    match encrypt.public_encrypt_file(path, "rsa_enkriptirana_datoteka") {
+                        Ok(_) => (),
                         Err(e) => {
                             self.error = Some(e);
-                            return;
                         }
-                    };
-                    self.do_write("rsa_enkriptirana_datoteka", &res);
+                    }
                 }
             }
             EncryptDecryptMessage::DecryptAsymmetric => {
                 if let (Some(decrypt), Some(path)) =
                     (self.asymmetric.as_ref(), self.selected_file.as_ref())
                 {
-                    let res = match decrypt.private_decrypt_file(path) {
-                        Ok(r) => r,
+                    match decrypt.private_decrypt_file(path, "rsa_dekriptirana_datoteka") {
+                        Ok(_) => (),
                         Err(e) => {
                             self.error = Some(e);
-                            return;
                         }
-                    };
-                    self.do_write("rsa_dekriptirana_datoteka", &res);
+                    }
                 }
             }
             EncryptDecryptMessage::EncryptSymmetric => {
-                if let (Some(encrypt), Some(path)) =
-                    (self.symmetric.as_ref(), self.selected_file.as_ref())
-                {
-                    let res = match encrypt.encrypt_file(path) {
-                        Ok(r) => r,
+                if let (Some(encrypt), Some(chunked), Some(path)) = (
+                    self.symmetric.as_ref(),
+                    self.chunked.as_ref(),
+                    self.selected_file.as_ref(),
+                ) {
+                    let res = if self.chunked_format {
+                        chunked.encrypt_file(path, "aes_enkriptirana_datoteka")
+                    } else {
+                        encrypt.encrypt_file(path, "aes_enkriptirana_datoteka")
+                    };
+                    match res {
+                        Ok(_) => (),
                         Err(e) => {
                             self.error = Some(e);
-                            return;
                         }
-                    };
-                    self.do_write("aes_enkriptirana_datoteka", &res);
+                    }
                 }
             }
             EncryptDecryptMessage::DecryptSymmetric => {
-                if let (Some(encrypt), Some(path)) =
-                    (self.symmetric.as_ref(), self.selected_file.as_ref())
-                {
-                    let res = match encrypt.decrypt_file(path) {
-                        Ok(r) => r,
+                if let (Some(encrypt), Some(chunked), Some(path)) = (
+                    self.symmetric.as_ref(),
+                    self.chunked.as_ref(),
+                    self.selected_file.as_ref(),
+                ) {
+                    let res = if is_chunked_file(path) {
+                        chunked.decrypt_file(path, "aes_dekriptirana_datoteka")
+                    } else {
+                        encrypt.decrypt_file(path, "aes_dekriptirana_datoteka")
+                    };
+                    match res {
+                        Ok(_) => (),
                         Err(e) => {
                             self.error = Some(e);
-                            return;
                         }
-                    };
-                    self.do_write("aes_dekriptirana_datoteka", &res);
+                    }
+                }
+            }
+            EncryptDecryptMessage::DecryptChunk => {
+                if let (Some(chunked), Some(path)) =
+                    (self.chunked.as_ref(), self.selected_file.as_ref())
+                {
+                    let res = prompt_number("Dekripcija dijela", "Unesite redni broj dijela", 0)
+                        .and_then(|index| {
+                            let mut reader = File::open(path)?;
+                            chunked.decrypt_chunk(&mut reader, index)
+                        })
+                        .and_then(|data| write_file("aes_dekriptirani_dio", &data, false));
+                    if let Err(e) = res {
+                        self.error = Some(e);
+                    }
+                }
+            }
+            EncryptDecryptMessage::DecryptRange => {
+                if let (Some(chunked), Some(path)) =
+                    (self.chunked.as_ref(), self.selected_file.as_ref())
+                {
+                    let res = prompt_number("Dekripcija raspona", "Unesite pocetni bajt", 0)
+                        .and_then(|offset| {
+                            let length = prompt_number(
+                                "Dekripcija raspona",
+                                "Unesite broj bajtova",
+                                self.chunk_size as u64,
+                            )?;
+                            let mut reader = File::open(path)?;
+                            write_file_streamed("aes_dekriptirani_raspon", |writer| {
+                                chunked.decrypt_range(&mut reader, writer, offset, length)
+                            })
+                        });
+                    if let Err(e) = res {
+                        self.error = Some(e);
+                    }
+                }
+            }
+            EncryptDecryptMessage::EncryptPassword => {
+                if let Some(path) = self.selected_file.as_ref() {
+                    let kdf = Kdf::new(self.kdf_algorithm, self.kdf_strength);
+                    let res = prompt_password(true).and_then(|password| {
+                        EncryptAes::encrypt_file_with_password(
+                            self.algorithm,
+                            kdf,
+                            &password,
+                            path,
+                            "lozinkom_enkriptirana_datoteka",
+                        )
+                    });
+                    if let Err(e) = res {
+                        self.error = Some(e);
+                    }
+                }
+            }
+            EncryptDecryptMessage::DecryptPassword => {
+                if let Some(path) = self.selected_file.as_ref() {
+                    let res = prompt_password(false).and_then(|password| {
+                        EncryptAes::decrypt_file_with_password(
+                            &password,
+                            path,
+                            "lozinkom_dekriptirana_datoteka",
+                        )
+                    });
+                    if let Err(e) = res {
+                        self.error = Some(e);
+                    }
                 }
             }
         }
     }
 
-    pub fn view(&self) -> Element<EncryptDecryptMessage> {
+    fn loaded_keys_info(&self) -> String {
+        let key_id = |fingerprint: anyhow::Result<Vec<u8>>| {
+            fingerprint
+                .map(|fingerprint| short_key_id(&fingerprint))
+                .unwrap_or_default()
+        };
+        match (self.asymmetric.as_ref(), self.symmetric.as_ref()) {
+            (Some(asymmetric), Some(symmetric)) => format!(
+                "Kljucevi su ucitani\nRSA kljuc: ID {}\nTajni kljuc: ID {}",
+                key_id(asymmetric.key_fingerprint()),
+                key_id(symmetric.key_fingerprint())
+            ),
+            _ => "Kljucevi su ucitani".to_string(),
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, EncryptDecryptMessage> {
         let load_keys_button = styled_button("Ucitaj kljuceve");
         let load_keys_button = if self.symmetric.is_none() || self.asymmetric.is_none() {
             widget::column![load_keys_button.on_press(EncryptDecryptMessage::LoadKeys)]
         } else {
-            widget::column![text("Kljucevi su ucitani"), load_keys_button].spacing(5)
+            widget::column![text(self.loaded_keys_info()), load_keys_button].spacing(5)
         };
 
         let load_file_button =
             styled_button("Odabir datoteke").on_press(EncryptDecryptMessage::LoadFile);
 
-        let mut row = styled_row().push(load_keys_button);
+        let algorithm_picker = widget::column![
+            text("Simetricni algoritam"),
+            pick_list(
+                &AeadAlgorithm::ALL[..],
+                Some(self.algorithm),
+                EncryptDecryptMessage::SelectAlgorithm
+            )
+        ]
+        .spacing(5);
+
+        let kdf_picker = widget::column![
+            text("Izvodenje kljuca iz lozinke"),
+            pick_list(
+                &KdfAlgorithm::ALL[..],
+                Some(self.kdf_algorithm),
+                EncryptDecryptMessage::SelectKdf
+            ),
+            pick_list(
+                &KdfStrength::ALL[..],
+                Some(self.kdf_strength),
+                EncryptDecryptMessage::SelectKdfStrength
+            )
+        ]
+        .spacing(5);
+
+        let label_editor = widget::column![
+            text(format!("Oznaka RSA-OAEP: \"{}\"", self.oaep_label)),
+            styled_button("Uredi oznaku").on_press(EncryptDecryptMessage::EditOaepLabel)
+        ]
+        .spacing(5);
+
+        let mut row = styled_row()
+            .push(algorithm_picker)
+            .push(kdf_picker)
+            .push(label_editor)
+            .push(load_keys_button);
 
         if let Some(path) = &self.selected_file {
             row = row.push(
@@ -174,7 +401,30 @@ impl EncryptDecryptView {
         if let Some(e) = &self.error {
             column = column.push(styled_error(e));
         };
-        column = column.push(row);
+        column = column
+            .push(row)
+            .push(
+                styled_row()
+                    .push(checkbox(
+                        "Simetricna enkripcija u dijelovima (dekripcija pojedinog dijela)",
+                        self.chunked_format,
+                        EncryptDecryptMessage::ChunkedFormat,
+                    ))
+                    .push(text(format!("Velicina dijela: {} B", self.chunk_size)))
+                    .push(
+                        styled_button("Promijeni velicinu dijela")
+                            .on_press(EncryptDecryptMessage::EditChunkSize),
+                    ),
+            )
+            .push(checkbox(
+                "Dopusti dekripciju starog RSA formata (PKCS#1 v1.5)",
+                self.allow_legacy,
+                EncryptDecryptMessage::AllowLegacy,
+            ));
+
+        if let Some(info) = &self.file_info {
+            column = column.push(text(info));
+        }
 
         if let (Some(_asymmetric), Some(_symmetric), Some(_selected_file)) = (
             self.asymmetric.as_ref(),
@@ -202,6 +452,78 @@ impl EncryptDecryptView {
             );
         }
 
+        // Pojedini dio ili raspon moze se dekriptirati samo iz datoteke u dijelovima
+        if let (Some(_chunked), Some(path)) = (self.chunked.as_ref(), self.selected_file.as_ref()) {
+            if is_chunked_file(path) {
+                column = column.push(
+                    styled_row()
+                        .push(
+                            styled_button("Dekriptiraj jedan dio")
+                                .on_press(EncryptDecryptMessage::DecryptChunk),
+                        )
+                        .push(
+                            styled_button("Dekriptiraj raspon bajtova")
+                                .on_press(EncryptDecryptMessage::DecryptRange),
+                        ),
+                );
+            }
+        }
+
+        // Za enkripciju lozinkom nisu potrebni kljucevi
+        if self.selected_file.is_some() {
+            column = column.push(
+                styled_row()
+                    .push(
+                        styled_button("Enkriptiraj datoteku lozinkom")
+                            .on_press(EncryptDecryptMessage::EncryptPassword),
+                    )
+                    .push(
+                        styled_button("Dekriptiraj datoteku lozinkom")
+                            .on_press(EncryptDecryptMessage::DecryptPassword),
+                    ),
+            );
+        }
+
         column.into()
     }
 }
+
+// Kod enkripcije se lozinka unosi dvaput
+fn prompt_password(confirm: bool) -> Result<String> {
+    let prompt = |message: &str| {
+        password_box("Lozinka datoteke", message).ok_or_else(|| anyhow!("Unos lozinke je prekinut"))
+    };
+    let password = prompt("Unesite lozinku")?;
+    if password.is_empty() {
+        return Err(anyhow!("Lozinka ne smije biti prazna"));
+    }
+    if confirm && prompt("Ponovite lozinku")? != password {
+        return Err(anyhow!("Lozinke se ne podudaraju"));
+    }
+    Ok(password)
+}
+
+fn prompt_number(title: &str, message: &str, default: u64) -> Result<u64> {
+    let input = input_box(title, message, &default.to_string())
+        .ok_or_else(|| anyhow!("Unos je prekinut"))?;
+    input
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("\"{}\" nije ispravan broj", input.trim()))
+}
+
+// Cita se samo zaglavlje datoteke
+fn file_info(path: &str) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    if is_chunked_file(path) {
+        return Some(match ChunkedHeader::read_from(&mut file) {
+            Ok(header) => header.to_string(),
+            Err(e) => e.to_string(),
+        });
+    }
+    match Container::read_from(&mut file, ContainerCipher::Aes256Cbc) {
+        Ok(container) if container.version == LEGACY_VERSION => None,
+        Ok(container) => Some(container.to_string()),
+        Err(e) => Some(e.to_string()),
+    }
+}
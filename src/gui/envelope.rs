@@ -0,0 +1,267 @@
+use iced::{
+    widget::{self, text},
+    Element,
+};
+use tinyfiledialogs::{input_box, open_file_dialog};
+
+use anyhow::Result;
+
+use crate::{
+    encryption::{load_public_key, DigitalEnvelope, DigitalSeal, EncryptRsa, MIN_RSA_KEY_LENGTH},
+    file_manip::write_file,
+    PUBLIC_KEY_FILENAME,
+};
+
+use super::{
+    path_to_filename, private_key_passphrase,
+    styled_components::{styled_button, styled_column, styled_error, styled_row},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum EnvelopeMessage {
+    LoadFile,
+    LoadRecipientKey,
+    EditLabel,
+    Create,
+    Open,
+    CreateSeal,
+    OpenSeal,
+}
+
+pub struct EnvelopeView {
+    selected_file: Option<String>,
+    public_key: Option<String>,
+    // Oznaka RSA-OAEP se zapisuje u omotnicu
+    label: String,
+    min_rsa_key_length: u32,
+    error: Option<anyhow::Error>,
+}
+
+impl EnvelopeView {
+    pub fn new() -> Self {
+        Self {
+            selected_file: None,
+            public_key: None,
+            label: String::new(),
+            min_rsa_key_length: MIN_RSA_KEY_LENGTH,
+            error: None,
+        }
+    }
+
+    pub fn set_min_rsa_key_length(&mut self, bits: u32) {
+        self.min_rsa_key_length = bits;
+    }
+
+    pub fn reset(&mut self) {
+        self.selected_file = None;
+        self.public_key = None;
+        self.error = None;
+    }
+
+    fn load_key_pair(&self) -> Result<EncryptRsa> {
+        let passphrase = private_key_passphrase(None)?;
+        EncryptRsa::from_files(None, passphrase.as_deref(), self.min_rsa_key_length)
+    }
+
+    fn do_write(&mut self, filename: &str, data: &[u8]) {
+        match write_file(filename, data, false) {
+            Ok(_) => (),
+            Err(e) => {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    pub fn update(&mut self, message: EnvelopeMessage) {
+        self.error = None;
+        match message {
+            EnvelopeMessage::LoadFile => {
+                self.selected_file = open_file_dialog("Odabir datoteke", "", None);
+            }
+            EnvelopeMessage::LoadRecipientKey => {
+                self.public_key = open_file_dialog("Odabir javnog kljuca", "", None);
+            }
+            EnvelopeMessage::EditLabel => {
+                if let Some(label) = input_box(
+                    "Oznaka RSA-OAEP",
+                    "Unesite oznaku (moze biti prazna)",
+                    &self.label,
+                ) {
+                    self.label = label;
+                }
+            }
+            EnvelopeMessage::Create => {
+                if let Some(path) = &self.selected_file {
+                    let recipient = match load_public_key(
+                        self.public_key.as_deref(),
+                        self.min_rsa_key_length,
+                    ) {
+                        Ok(r) => r,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    };
+                    let label = self.label.as_bytes();
+                    let res = match DigitalEnvelope::create_file(path, &recipient, label) {
+                        Ok(r) => r,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    };
+                    self.do_write("digitalna_omotnica", &res);
+                }
+            }
+            EnvelopeMessage::Open => {
+                if let Some(path) = &self.selected_file {
+                    let recipient = match self.load_key_pair() {
+                        Ok(rsa) => rsa,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    };
+                    let res = match DigitalEnvelope::open_file(path, &recipient) {
+                        Ok(r) => r,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    };
+                    self.do_write("otvorena_omotnica", &res);
+                }
+            }
+            EnvelopeMessage::CreateSeal => {
+                if let Some(path) = &self.selected_file {
+                    let recipient = match load_public_key(
+                        self.public_key.as_deref(),
+                        self.min_rsa_key_length,
+                    ) {
+                        Ok(r) => r,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    };
+                    let sender = match self.load_key_pair() {
+                        Ok(rsa) => rsa,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    };
+                    let res = match DigitalSeal::create_file(
+                        path,
+                        &recipient,
+                        &sender,
+                        self.label.as_bytes(),
+                    ) {
+                        Ok(r) => r,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    };
+                    self.do_write("digitalni_pecat", &res);
+                }
+            }
+            EnvelopeMessage::OpenSeal => {
+                if let Some(path) = &self.selected_file {
+                    let sender = match load_public_key(
+                        self.public_key.as_deref(),
+                        self.min_rsa_key_length,
+                    ) {
+                        Ok(r) => r,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    };
+                    let recipient = match self.load_key_pair() {
+                        Ok(rsa) => rsa,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    };
+                    let res = match DigitalSeal::open_file(path, &recipient, &[sender]) {
+                        Ok(r) => r,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    };
+                    self.do_write("otvoreni_pecat", &res);
+                }
+            }
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, EnvelopeMessage> {
+        let load_file_button = styled_button("Odabir datoteke").on_press(EnvelopeMessage::LoadFile);
+        let load_key_button =
+            styled_button("Odabir javnog kljuca").on_press(EnvelopeMessage::LoadRecipientKey);
+
+        let mut row = styled_row();
+
+        if let Some(path) = &self.selected_file {
+            row = row.push(
+                widget::column![
+                    text(format!("Datoteka: {}", path_to_filename(path))),
+                    load_file_button
+                ]
+                .spacing(5),
+            );
+        } else {
+            row = row.push(load_file_button)
+        }
+
+        let public_key = match &self.public_key {
+            Some(path) => path_to_filename(path),
+            None => PUBLIC_KEY_FILENAME.to_owned(),
+        };
+        row = row.push(
+            widget::column![
+                text(format!("Javni kljuc: {}", public_key)),
+                load_key_button
+            ]
+            .spacing(5),
+        );
+        row = row.push(
+            widget::column![
+                text(format!("Oznaka RSA-OAEP: \"{}\"", self.label)),
+                styled_button("Uredi oznaku").on_press(EnvelopeMessage::EditLabel)
+            ]
+            .spacing(5),
+        );
+
+        let mut column = styled_column(None);
+        if let Some(e) = &self.error {
+            column = column.push(styled_error(e));
+        };
+        column = column.push(row);
+
+        if self.selected_file.is_some() {
+            column = column.push(
+                styled_row()
+                    .push(
+                        styled_button("Kreiraj digitalnu omotnicu")
+                            .on_press(EnvelopeMessage::Create),
+                    )
+                    .push(
+                        styled_button("Otvori digitalnu omotnicu").on_press(EnvelopeMessage::Open),
+                    )
+                    .push(
+                        styled_button("Kreiraj digitalni pecat")
+                            .on_press(EnvelopeMessage::CreateSeal),
+                    )
+                    .push(
+                        styled_button("Otvori digitalni pecat").on_press(EnvelopeMessage::OpenSeal),
+                    ),
+            );
+        }
+
+        column.into()
+    }
+}
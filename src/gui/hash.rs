@@ -1,40 +1,59 @@
 use iced::{
-    widget::{self, text},
+    widget::{self, checkbox, pick_list, text},
     Element,
 };
 use tinyfiledialogs::open_file_dialog;
 
-use crate::{encryption::ShaHash, file_manip::write_file};
+use crate::{
+    file_manip::write_file,
+    hashing::{format_digest, verify_digest_file, HashAlgorithm},
+};
 
 use super::{
     path_to_filename,
-    styled_components::{styled_button, styled_column, styled_error, styled_row},
+    styled_components::{styled_button, styled_column, styled_error, styled_row, GREEN, RED},
 };
 
+const DIGEST_FILENAME: &str = "sazetak.txt";
+
 #[derive(Debug, Clone, Copy)]
 pub enum HashMessage {
     LoadFile,
+    LoadDigest,
+    SelectAlgorithm(HashAlgorithm),
+    TextMode(bool),
     Hash,
+    Verify,
 }
 
 pub struct HashView {
+    algorithm: HashAlgorithm,
+    text_mode: bool,
     selected_file: Option<String>,
+    selected_digest: Option<String>,
     file_hash: Option<String>,
+    file_verified: Option<bool>,
     error: Option<anyhow::Error>,
 }
 
 impl HashView {
     pub fn new() -> Self {
         Self {
+            algorithm: HashAlgorithm::Sha256,
+            text_mode: false,
             selected_file: None,
+            selected_digest: None,
             file_hash: None,
+            file_verified: None,
             error: None,
         }
     }
 
     pub fn reset(&mut self) {
         self.selected_file = None;
+        self.selected_digest = None;
         self.file_hash = None;
+        self.file_verified = None;
         self.error = None;
     }
 
@@ -43,19 +62,38 @@ impl HashView {
         match message {
             HashMessage::LoadFile => {
                 self.file_hash = None;
+                self.file_verified = None;
                 self.selected_file = open_file_dialog("Odabir datoteke", "", None);
             }
+            HashMessage::LoadDigest => {
+                self.file_verified = None;
+                self.selected_digest = open_file_dialog("Odabir zapisa sazetka", "", None);
+            }
+            HashMessage::SelectAlgorithm(algorithm) => {
+                self.algorithm = algorithm;
+                self.file_hash = None;
+            }
+            HashMessage::TextMode(text_mode) => {
+                self.text_mode = text_mode;
+                self.file_hash = None;
+                self.file_verified = None;
+            }
             HashMessage::Hash => {
                 if let Some(path) = &self.selected_file {
-                    let hash = match ShaHash::hash_file(path) {
+                    let hash = if self.text_mode {
+                        self.algorithm.hash_text_file(path)
+                    } else {
+                        self.algorithm.hash_file(path)
+                    };
+                    let hash = match hash {
                         Ok(hash) => hash,
                         Err(e) => {
                             self.error = Some(e);
                             return;
                         }
                     };
-                    let hash_str = hex::encode(&hash);
-                    match write_file("sazetak.txt", hash_str.as_bytes(), false) {
+                    let hash_str = format_digest(self.algorithm, &path_to_filename(path), &hash);
+                    match write_file(DIGEST_FILENAME, hash_str.as_bytes(), false) {
                         Ok(_) => {}
                         Err(e) => {
                             self.error = Some(e);
@@ -65,12 +103,24 @@ impl HashView {
                     self.file_hash = Some(hash_str);
                 }
             }
+            HashMessage::Verify => {
+                if let Some(path) = &self.selected_file {
+                    let digest_path = self.selected_digest.as_deref().unwrap_or(DIGEST_FILENAME);
+                    match verify_digest_file(path, digest_path, self.text_mode) {
+                        Ok(verified) => self.file_verified = Some(verified),
+                        Err(e) => {
+                            self.error = Some(e);
+                        }
+                    }
+                }
+            }
         }
     }
 
-    pub fn view(&self) -> Element<HashMessage> {
+    pub fn view(&self) -> Element<'_, HashMessage> {
         let load_file_button = styled_button("Odabir datoteke").on_press(HashMessage::LoadFile);
         let hash_button = styled_button("Izracun sazetka").on_press(HashMessage::Hash);
+        let verify_button = styled_button("Provjera sazetka").on_press(HashMessage::Verify);
 
         let mut row = styled_row();
 
@@ -86,14 +136,42 @@ impl HashView {
             row = row.push(load_file_button)
         }
 
-        row = row.push(hash_button);
+        let digest = match &self.selected_digest {
+            Some(path) => path_to_filename(path),
+            None => DIGEST_FILENAME.to_owned(),
+        };
+        row = row.push(
+            widget::column![
+                text(format!("Zapis sazetka: {}", digest)),
+                styled_button("Odabir zapisa").on_press(HashMessage::LoadDigest)
+            ]
+            .spacing(5),
+        );
+        row = row.push(
+            widget::column![
+                text("Algoritam sazetka"),
+                pick_list(
+                    &HashAlgorithm::ALL[..],
+                    Some(self.algorithm),
+                    HashMessage::SelectAlgorithm
+                )
+            ]
+            .spacing(5),
+        );
         let mut column = styled_column(None);
 
         if let Some(e) = &self.error {
             column = column.push(styled_error(e));
         };
 
-        column = column.push(row);
+        column = column
+            .push(row)
+            .push(checkbox(
+                "Tekstualni nacin (zavrseci redaka se normaliziraju)",
+                self.text_mode,
+                HashMessage::TextMode,
+            ))
+            .push(styled_row().push(hash_button).push(verify_button));
 
         // let mut column = widget::column![row![load_file_button, hash_button]];
 
@@ -101,6 +179,14 @@ impl HashView {
             column = column.push(text(hash));
         }
 
+        if let Some(verified) = self.file_verified {
+            if verified {
+                column = column.push(text("Sazetak odgovara datoteci.").style(GREEN));
+            } else {
+                column = column.push(text("Sazetak ne odgovara datoteci.").style(RED));
+            }
+        }
+
         column.into()
     }
 }
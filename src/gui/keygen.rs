@@ -1,34 +1,91 @@
 use std::fs::File;
 
+use anyhow::{anyhow, Result};
 use iced::{
-    widget::{self, text},
+    widget::{self, checkbox, pick_list, text},
     Element,
 };
+use tinyfiledialogs::{message_box_yes_no, open_file_dialog, MessageBoxIcon, YesNo};
 
 use crate::{
-    encryption::EncryptRsa, file_manip::write_file, keygen::Keygen, PRIVATE_KEY_FILENAME,
-    PUBLIC_KEY_FILENAME, SECRET_KEY_FILENAME,
+    encryption::{load_secret_key, RsaKeyParams, ShaHash, MIN_RSA_KEY_LENGTH, RSA_KEY_LENGTHS},
+    file_manip::{read_file_to_buffer, write_file},
+    fingerprint::{format_fingerprint, key_fingerprint, key_title, randomart, short_key_id},
+    key_format::{export_private_key, export_public_key, import_public_key, KeyFormat},
+    keygen::Keygen,
+    private_key::{change_passphrase, is_encrypted, load_private_key, save_private_key},
+    signing::{check_key_length, generate_save_keys, KeyAlgorithm},
+    PRIVATE_KEY_FILENAME, PUBLIC_KEY_FILENAME, SECRET_KEY_FILENAME,
 };
 
-use super::styled_components::{styled_button, styled_column, styled_error, styled_row};
+use super::{
+    certificate::{CertificateMessage, CertificateView},
+    private_key_passphrase, prompt_passphrase,
+    styled_components::{styled_button, styled_column, styled_error, styled_randomart, styled_row},
+};
+
+const EXPORTED_PUBLIC_KEY_FILENAME: &str = "izvezeni_javni_kljuc";
+const EXPORTED_PRIVATE_KEY_FILENAME: &str = "izvezeni_privatni_kljuc";
+const IMPORTED_PUBLIC_KEY_FILENAME: &str = "uvezeni_javni_kljuc.pem";
 
 #[derive(Debug, Clone, Copy)]
 pub enum KeyGenMessage {
+    SelectAlgorithm(KeyAlgorithm),
+    SelectRsaKeyLength(u32),
+    SelectMinRsaKeyLength(u32),
+    ProtectPrivateKey(bool),
     GenerateSecretKey,
     GenerateKeyPair,
+    ChangePassphrase,
+    SelectExportFormat(KeyFormat),
+    ExportPublicKey,
+    ExportPrivateKey,
+    ImportKeyPair,
+    ImportPublicKey,
+    Certificate(CertificateMessage),
 }
 
 pub struct GenerateKeysView {
+    algorithm: KeyAlgorithm,
+    rsa_params: RsaKeyParams,
+    // Minimum vrijedi za ucitavanje kljuceva u svim karticama
+    min_rsa_key_length: u32,
+    protect_private_key: bool,
+    export_format: KeyFormat,
+    info: Option<String>,
+    certificate_view: CertificateView,
     error: Option<anyhow::Error>,
 }
 
 impl GenerateKeysView {
     pub fn new() -> Self {
-        Self { error: None }
+        Self {
+            algorithm: KeyAlgorithm::Rsa,
+            rsa_params: RsaKeyParams::default(),
+            min_rsa_key_length: MIN_RSA_KEY_LENGTH,
+            protect_private_key: false,
+            export_format: KeyFormat::Pem,
+            info: None,
+            certificate_view: CertificateView::new(),
+            error: None,
+        }
     }
 
     pub fn update(&mut self, message: KeyGenMessage) {
+        self.info = None;
         match message {
+            KeyGenMessage::SelectAlgorithm(algorithm) => {
+                self.algorithm = algorithm;
+            }
+            KeyGenMessage::SelectRsaKeyLength(bits) => {
+                self.rsa_params.bits = bits;
+            }
+            KeyGenMessage::SelectMinRsaKeyLength(bits) => {
+                self.min_rsa_key_length = bits;
+            }
+            KeyGenMessage::ProtectPrivateKey(protect) => {
+                self.protect_private_key = protect;
+            }
             KeyGenMessage::GenerateSecretKey => {
                 let key = Keygen::default().generate_256bit_key();
                 match write_file(SECRET_KEY_FILENAME, &key, false) {
@@ -39,24 +96,77 @@ impl GenerateKeysView {
                 }
             }
             KeyGenMessage::GenerateKeyPair => {
-                match EncryptRsa::new_save_keys() {
+                let passphrase = if self.protect_private_key {
+                    match prompt_new_passphrase(false) {
+                        Ok(passphrase) => passphrase,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    }
+                } else {
+                    None
+                };
+                match generate_save_keys(self.algorithm, self.rsa_params, passphrase.as_deref()) {
                     Ok(_) => {}
                     Err(e) => {
                         self.error = Some(e);
                     }
                 };
             }
+            KeyGenMessage::ChangePassphrase => match change_private_key_passphrase() {
+                Ok(_) => {}
+                Err(e) => {
+                    self.error = Some(e);
+                }
+            },
+            KeyGenMessage::SelectExportFormat(format) => {
+                self.export_format = format;
+            }
+            KeyGenMessage::ExportPublicKey | KeyGenMessage::ExportPrivateKey => {
+                let private = matches!(message, KeyGenMessage::ExportPrivateKey);
+                match export_key(private, self.export_format) {
+                    Ok(filename) => {
+                        self.info = Some(format!("Kljuc je izvezen u datoteku {}", filename));
+                    }
+                    Err(e) => {
+                        self.error = Some(e);
+                    }
+                }
+            }
+            KeyGenMessage::ImportKeyPair | KeyGenMessage::ImportPublicKey => {
+                let private = matches!(message, KeyGenMessage::ImportKeyPair);
+                match import_key(private, self.protect_private_key, self.min_rsa_key_length) {
+                    Ok(info) => {
+                        self.info = info;
+                    }
+                    Err(e) => {
+                        self.error = Some(e);
+                    }
+                }
+            }
+            KeyGenMessage::Certificate(msg) => self.certificate_view.update(msg),
         }
     }
 
-    pub fn view(&self) -> Element<KeyGenMessage> {
+    pub fn view(&self) -> Element<'_, KeyGenMessage> {
         let mut row = styled_row();
         let secret_key_button =
             styled_button("Generiraj tajni kljuc").on_press(KeyGenMessage::GenerateSecretKey);
         let keypair_button =
             styled_button("Generiraj par kljuceva").on_press(KeyGenMessage::GenerateKeyPair);
         row = if File::open(SECRET_KEY_FILENAME).is_ok() {
-            row.push(widget::column![text("Tajni kljuc vec postoji"), secret_key_button].spacing(5))
+            let secret_key_id = load_secret_key(None)
+                .and_then(|key| ShaHash::hash(&key))
+                .map(|fingerprint| format!(" (ID {})", short_key_id(&fingerprint)))
+                .unwrap_or_default();
+            row.push(
+                widget::column![
+                    text(format!("Tajni kljuc vec postoji{}", secret_key_id)),
+                    secret_key_button
+                ]
+                .spacing(5),
+            )
         } else {
             row.push(secret_key_button)
         };
@@ -68,11 +178,237 @@ impl GenerateKeysView {
             row.push(keypair_button)
         };
 
+        let protected = read_file_to_buffer(PRIVATE_KEY_FILENAME)
+            .map(|pem| is_encrypted(&pem))
+            .unwrap_or(false);
+        let passphrase_row = styled_row()
+            .push(checkbox(
+                "Zastiti privatni kljuc lozinkom",
+                self.protect_private_key,
+                KeyGenMessage::ProtectPrivateKey,
+            ))
+            .push(
+                widget::column![
+                    text(if protected {
+                        "Privatni kljuc je zasticen lozinkom"
+                    } else {
+                        "Privatni kljuc nije zasticen lozinkom"
+                    }),
+                    styled_button("Promijeni lozinku").on_press(KeyGenMessage::ChangePassphrase)
+                ]
+                .spacing(5),
+            );
+
+        row = row.push(
+            widget::column![
+                text("Algoritam para kljuceva"),
+                pick_list(
+                    &KeyAlgorithm::ALL[..],
+                    Some(self.algorithm),
+                    KeyGenMessage::SelectAlgorithm
+                )
+            ]
+            .spacing(5),
+        );
+
+        let mut rsa_row = styled_row();
+        if self.algorithm == KeyAlgorithm::Rsa {
+            rsa_row = rsa_row.push(
+                widget::column![
+                    text("Velicina RSA kljuca (bita)"),
+                    pick_list(
+                        &RSA_KEY_LENGTHS[..],
+                        Some(self.rsa_params.bits),
+                        KeyGenMessage::SelectRsaKeyLength
+                    )
+                ]
+                .spacing(5),
+            );
+        }
+        rsa_row = rsa_row.push(
+            widget::column![
+                text("Minimalna velicina RSA kljuca pri ucitavanju"),
+                pick_list(
+                    &RSA_KEY_LENGTHS[..],
+                    Some(self.min_rsa_key_length),
+                    KeyGenMessage::SelectMinRsaKeyLength
+                )
+            ]
+            .spacing(5),
+        );
+
+        let format_row = styled_row()
+            .push(
+                widget::column![
+                    text("Format izvoza kljuca"),
+                    pick_list(
+                        &KeyFormat::ALL[..],
+                        Some(self.export_format),
+                        KeyGenMessage::SelectExportFormat
+                    )
+                ]
+                .spacing(5),
+            )
+            .push(styled_button("Izvezi javni kljuc").on_press(KeyGenMessage::ExportPublicKey))
+            .push(styled_button("Izvezi privatni kljuc").on_press(KeyGenMessage::ExportPrivateKey))
+            .push(styled_button("Uvezi par kljuceva").on_press(KeyGenMessage::ImportKeyPair))
+            .push(styled_button("Uvezi javni kljuc").on_press(KeyGenMessage::ImportPublicKey));
+
         let mut column = styled_column(None);
 
         if let Some(e) = &self.error {
             column = column.push(styled_error(e));
         }
-        column.push(row).into()
+        if let Some(info) = &self.info {
+            column = column.push(text(info));
+        }
+        column = column
+            .push(row)
+            .push(rsa_row)
+            .push(passphrase_row)
+            .push(format_row);
+        if let Some(identity) = public_key_identity() {
+            column = column.push(identity);
+        }
+        column
+            .push(self.certificate_view.view().map(KeyGenMessage::Certificate))
+            .into()
+    }
+}
+
+// Otisak, ID i slika otiska vlastitog javnog kljuca za usporedbu kljuceva
+fn public_key_identity<'a>() -> Option<Element<'a, KeyGenMessage>> {
+    let pkey = import_public_key(&read_file_to_buffer(PUBLIC_KEY_FILENAME).ok()?).ok()?;
+    let fingerprint = key_fingerprint(&pkey).ok()?;
+    let title = key_title(&pkey).ok()?;
+    let details = widget::column![
+        text(format!(
+            "Javni kljuc: {}, ID {}",
+            title,
+            short_key_id(&fingerprint)
+        )),
+        text(format!(
+            "Otisak (SHA-256): {}",
+            format_fingerprint(&fingerprint)
+        )),
+    ]
+    .spacing(5);
+    Some(
+        styled_row()
+            .push(styled_randomart(&randomart(&fingerprint, &title)))
+            .push(details)
+            .into(),
+    )
+}
+
+// Nova lozinka se unosi dva puta
+pub(super) fn prompt_new_passphrase(allow_empty: bool) -> Result<Option<String>> {
+    let message = if allow_empty {
+        "Unesite novu lozinku (prazna lozinka uklanja zastitu)"
+    } else {
+        "Unesite novu lozinku"
+    };
+    let passphrase = prompt_passphrase(message)?;
+    if passphrase.is_empty() {
+        if allow_empty {
+            return Ok(None);
+        }
+        return Err(anyhow!("Lozinka ne smije biti prazna"));
+    }
+    if prompt_passphrase("Ponovite novu lozinku")? != passphrase {
+        return Err(anyhow!("Lozinke se ne podudaraju"));
+    }
+    Ok(Some(passphrase))
+}
+
+fn change_private_key_passphrase() -> Result<()> {
+    let pem = read_file_to_buffer(PRIVATE_KEY_FILENAME)?;
+    let old_passphrase = if is_encrypted(&pem) {
+        Some(prompt_passphrase("Unesite trenutnu lozinku")?)
+    } else {
+        None
+    };
+    let new_passphrase = prompt_new_passphrase(true)?;
+    change_passphrase(None, old_passphrase.as_deref(), new_passphrase.as_deref())
+}
+
+fn export_key(private: bool, format: KeyFormat) -> Result<String> {
+    let (filename, data) = if private {
+        let pkey = load_private_key(None, private_key_passphrase(None)?.as_deref())?;
+        let passphrase = if format.supports_encryption() {
+            prompt_new_passphrase(false)?
+        } else {
+            confirm_unprotected_export(format)?;
+            None
+        };
+        (
+            EXPORTED_PRIVATE_KEY_FILENAME,
+            export_private_key(&pkey, format, passphrase.as_deref())?,
+        )
+    } else {
+        let pkey = import_public_key(&read_file_to_buffer(PUBLIC_KEY_FILENAME)?)?;
+        (
+            EXPORTED_PUBLIC_KEY_FILENAME,
+            export_public_key(&pkey, format)?,
+        )
+    };
+    let filename = format!("{}.{}", filename, format.extension());
+    write_file(&filename, &data, false)?;
+    Ok(filename)
+}
+
+// Formati bez zastite lozinkom izvoze se tek nakon potvrde korisnika,
+// a OpenSSH izvoz privatnog kljuca ionako se odbija
+fn confirm_unprotected_export(format: KeyFormat) -> Result<()> {
+    if format == KeyFormat::OpenSsh {
+        return Ok(());
+    }
+    let message = format!(
+        "Format {} ne podrzava zastitu lozinkom, privatni kljuc ce biti spremljen nezasticen. Nastaviti?",
+        format
+    );
+    match message_box_yes_no(
+        "Izvoz privatnog kljuca",
+        &message,
+        MessageBoxIcon::Warning,
+        YesNo::No,
+    ) {
+        YesNo::Yes => Ok(()),
+        YesNo::No => Err(anyhow!("Izvoz privatnog kljuca je prekinut")),
+    }
+}
+
+// Uvezeni privatni kljuc postaje par kljuceva aplikacije,
+// a javni kljuc se samo pretvara u PEM kako bi se mogao odabrati u ostalim karticama.
+// RSA kljucevi manji od odabranog minimuma se ne uvoze jer se kasnije ne bi mogli ucitati
+fn import_key(private: bool, protect: bool, min_rsa_key_length: u32) -> Result<Option<String>> {
+    let path = match open_file_dialog("Odabir kljuca za uvoz", "", None) {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let format = KeyFormat::detect(&read_file_to_buffer(&path)?);
+    if private {
+        let passphrase = private_key_passphrase(Some(&path))?;
+        let pkey = load_private_key(Some(&path), passphrase.as_deref())?;
+        check_key_length(&pkey, min_rsa_key_length)?;
+        let passphrase = if protect {
+            prompt_new_passphrase(false)?
+        } else {
+            None
+        };
+        save_private_key(&pkey, None, passphrase.as_deref())?;
+        write_file(PUBLIC_KEY_FILENAME, &pkey.public_key_to_pem()?, false)?;
+        return Ok(Some(format!("Uvezen je par kljuceva (format: {})", format)));
     }
+    let pkey = import_public_key(&read_file_to_buffer(&path)?)?;
+    check_key_length(&pkey, min_rsa_key_length)?;
+    write_file(
+        IMPORTED_PUBLIC_KEY_FILENAME,
+        &pkey.public_key_to_pem()?,
+        false,
+    )?;
+    Ok(Some(format!(
+        "Javni kljuc (format: {}) je spremljen u datoteku {}",
+        format, IMPORTED_PUBLIC_KEY_FILENAME
+    )))
 }
@@ -1,6 +1,20 @@
 use std::path::Path;
 
+use anyhow::{anyhow, Result};
+use tinyfiledialogs::password_box;
+
+use crate::{
+    file_manip::read_file_to_buffer,
+    fingerprint::{key_fingerprint, key_title, short_key_id},
+    key_format::import_public_key,
+    private_key::is_encrypted,
+    PRIVATE_KEY_FILENAME,
+};
+
+pub mod certificate;
+pub mod certificate_authority;
 pub mod encrypt_decrypt;
+pub mod envelope;
 pub mod hash;
 pub mod keygen;
 pub mod navigation;
@@ -14,3 +28,29 @@ pub(super) fn path_to_filename(path: &str) -> String {
         "".to_owned()
     }
 }
+
+pub(super) fn prompt_passphrase(message: &str) -> Result<String> {
+    password_box("Lozinka privatnog kljuca", message)
+        .ok_or_else(|| anyhow!("Unos lozinke je prekinut"))
+}
+
+// Lozinka se trazi samo ako je privatni kljuc zasticen, a greske citanja prijavljuje ucitavanje kljuca
+pub(super) fn private_key_passphrase(filename: Option<&str>) -> Result<Option<String>> {
+    match read_file_to_buffer(filename.unwrap_or(PRIVATE_KEY_FILENAME)) {
+        Ok(data) if is_encrypted(&data) => {
+            prompt_passphrase("Unesite lozinku privatnog kljuca").map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+// Vrsta i ID javnog kljuca iz datoteke, prikazuje se uz odabrani kljuc
+pub(super) fn public_key_id(filename: &str) -> Option<String> {
+    let pkey = import_public_key(&read_file_to_buffer(filename).ok()?).ok()?;
+    let fingerprint = key_fingerprint(&pkey).ok()?;
+    Some(format!(
+        "{}, ID {}",
+        key_title(&pkey).ok()?,
+        short_key_id(&fingerprint)
+    ))
+}
@@ -6,8 +6,10 @@ use super::styled_components::{styled_button, styled_row};
 pub enum NavigationStateMessage {
     KeyGen,
     EncryptDecrypt,
+    Envelope,
     Hashing,
     Sign,
+    CertificateAuthority,
 }
 
 pub struct NavigationButtons {
@@ -25,15 +27,20 @@ impl NavigationButtons {
         self.current_state = state;
     }
 
-    pub fn view(&self) -> Element<NavigationStateMessage> {
+    pub fn view(&self) -> Element<'_, NavigationStateMessage> {
         styled_row()
             .push(styled_button("Generiranje kljuceva").on_press(NavigationStateMessage::KeyGen))
             .push(
                 styled_button("Enkripcija / dekripcija")
                     .on_press(NavigationStateMessage::EncryptDecrypt),
             )
+            .push(styled_button("Omotnica / pecat").on_press(NavigationStateMessage::Envelope))
             .push(styled_button("Sazetak").on_press(NavigationStateMessage::Hashing))
             .push(styled_button("Potpis").on_press(NavigationStateMessage::Sign))
+            .push(
+                styled_button("Certifikacijsko tijelo")
+                    .on_press(NavigationStateMessage::CertificateAuthority),
+            )
             .into()
     }
 }
@@ -1,13 +1,32 @@
+use anyhow::Result;
 use iced::{
-    widget::{self, text},
+    widget::{self, checkbox, pick_list, text},
     Element,
 };
 use tinyfiledialogs::open_file_dialog;
 
-use crate::{encryption::EncryptRsa, file_manip::write_file};
+use crate::{
+    certificate::{load_certificate, load_certificate_chain, name_to_string},
+    crl::load_crl,
+    encryption::MIN_RSA_KEY_LENGTH,
+    file_manip::{read_file_to_buffer, write_file},
+    hashing::HashAlgorithm,
+    signing::{
+        SignatureMode, SignaturePadding, SigningKey, Verification, VerificationOutcome, VerifyKey,
+    },
+    trust_store::TrustStore,
+    ISSUED_CERTIFICATE_FILENAME, PUBLIC_KEY_FILENAME,
+};
+
+const SIGNATURE_FILENAME: &str = "potpis";
+// Potpis bez zaglavlja koji se moze provjeriti i naredbom `openssl dgst -verify`
+const DETACHED_SIGNATURE_FILENAME: &str = "potpis.sig";
+const ATTACHED_FILENAME: &str = "potpisana_poruka";
+const ARMORED_ATTACHED_FILENAME: &str = "potpisana_poruka.asc";
+const EXTRACTED_FILENAME: &str = "izdvojena_poruka";
 
 use super::{
-    path_to_filename,
+    path_to_filename, private_key_passphrase, public_key_id,
     styled_components::{styled_button, styled_column, styled_error, styled_row, GREEN, RED},
 };
 
@@ -15,74 +34,154 @@ use super::{
 pub enum LoadFileType {
     File,
     Signature,
+    PublicKey,
+    Certificate,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum SignMessage {
     LoadFile(LoadFileType),
+    SelectPadding(SignaturePadding),
+    SelectDigest(HashAlgorithm),
+    SelectMode(SignatureMode),
+    Armored(bool),
+    AttachCertificates(bool),
+    UseTrustedRoots(bool),
+    AddTrustedRoot,
+    AddCrl,
     Sign,
     Verify,
+    SignAttached,
+    VerifyAttached,
 }
 
 pub struct SignView {
+    padding: SignaturePadding,
+    digest: HashAlgorithm,
+    mode: SignatureMode,
+    armored: bool,
+    attach_certificates: bool,
+    use_trusted_roots: bool,
+    min_rsa_key_length: u32,
     selected_file: Option<String>,
     selected_signature: Option<String>,
-    file_verified: Option<bool>,
+    selected_public_key: Option<String>,
+    selected_certificate: Option<String>,
+    verification: Option<Verification>,
+    message_extracted: bool,
+    info: Option<String>,
     error: Option<anyhow::Error>,
 }
 
 impl SignView {
     pub fn new() -> Self {
         Self {
+            padding: SignaturePadding::Pkcs1,
+            digest: HashAlgorithm::Sha256,
+            mode: SignatureMode::Binary,
+            armored: false,
+            attach_certificates: false,
+            use_trusted_roots: false,
+            min_rsa_key_length: MIN_RSA_KEY_LENGTH,
             selected_file: None,
             selected_signature: None,
-            file_verified: None,
+            selected_public_key: None,
+            selected_certificate: None,
+            verification: None,
+            message_extracted: false,
+            info: None,
             error: None,
         }
     }
 
+    pub fn set_min_rsa_key_length(&mut self, bits: u32) {
+        self.min_rsa_key_length = bits;
+    }
+
     pub fn reset(&mut self) {
         self.selected_file = None;
         self.selected_signature = None;
-        self.file_verified = None;
+        self.selected_public_key = None;
+        self.selected_certificate = None;
+        self.verification = None;
+        self.message_extracted = false;
+        self.info = None;
         self.error = None;
     }
 
     pub fn update(&mut self, message: SignMessage) {
         self.error = None;
-        let rsa = match EncryptRsa::from_files(None) {
-            Ok(rsa) => rsa,
-            Err(e) => {
-                self.error = Some(e);
-                return;
-            }
-        };
+        self.info = None;
+        self.message_extracted = false;
         match message {
+            SignMessage::SelectPadding(padding) => {
+                self.padding = padding;
+                self.verification = None;
+            }
+            SignMessage::SelectDigest(digest) => {
+                self.digest = digest;
+                self.verification = None;
+            }
+            SignMessage::SelectMode(mode) => {
+                self.mode = mode;
+                self.verification = None;
+            }
+            SignMessage::Armored(armored) => {
+                self.armored = armored;
+            }
+            SignMessage::AttachCertificates(attach) => {
+                self.attach_certificates = attach;
+            }
+            SignMessage::UseTrustedRoots(use_trusted_roots) => {
+                self.use_trusted_roots = use_trusted_roots;
+                self.verification = None;
+            }
+            SignMessage::AddTrustedRoot => {
+                if let Err(e) = self.add_trusted_root() {
+                    self.error = Some(e);
+                }
+            }
+            SignMessage::AddCrl => {
+                if let Err(e) = self.add_crl() {
+                    self.error = Some(e);
+                }
+            }
             SignMessage::LoadFile(f) => match f {
                 LoadFileType::File => {
-                    self.file_verified = None;
+                    self.verification = None;
                     self.selected_file = open_file_dialog("Odabir datoteke", "", None);
                 }
                 LoadFileType::Signature => {
-                    self.file_verified = None;
+                    self.verification = None;
                     self.selected_signature =
                         open_file_dialog("Odabir datoteke s potpisom", "", None);
                 }
+                LoadFileType::PublicKey => {
+                    self.verification = None;
+                    self.selected_public_key =
+                        open_file_dialog("Odabir javnog kljuca potpisnika", "", None);
+                }
+                LoadFileType::Certificate => {
+                    self.selected_certificate =
+                        open_file_dialog("Odabir certifikata potpisnika s lancem", "", None);
+                }
             },
             SignMessage::Sign => {
                 if let Some(path) = &self.selected_file {
-                    let signature = match rsa.sign_file(path) {
-                        Ok(sig) => sig,
+                    let signer = match self.signing_key() {
+                        Ok(signer) => signer,
                         Err(e) => {
                             self.error = Some(e);
                             return;
                         }
                     };
-                    match write_file("potpis", &signature, false) {
-                        Ok(_) => (),
-                        Err(e) => {
-                            self.error = Some(e);
-                        }
+                    let written = signer.sign_file(path).and_then(|signature| {
+                        write_file(SIGNATURE_FILENAME, &signature, false)?;
+                        let detached = signer.sign_file_detached(path)?;
+                        write_file(DETACHED_SIGNATURE_FILENAME, &detached, false)
+                    });
+                    if let Err(e) = written {
+                        self.error = Some(e);
                     }
                 }
             }
@@ -90,20 +189,124 @@ impl SignView {
                 if let (Some(file_path), Some(signature_path)) =
                     (&self.selected_file, &self.selected_signature)
                 {
-                    let verify = match rsa.verify_file_signature(file_path, signature_path) {
-                        Ok(v) => v,
-                        Err(_) => {
-                            self.file_verified = Some(false);
+                    let verifier = match self.verify_key() {
+                        Ok(verifier) => verifier,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    };
+                    self.verification =
+                        Some(verifier.verify_file_signature(file_path, signature_path));
+                }
+            }
+            SignMessage::SignAttached => {
+                if let Some(path) = &self.selected_file {
+                    let signer = match self.signing_key() {
+                        Ok(signer) => signer,
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    };
+                    let output = if self.armored {
+                        ARMORED_ATTACHED_FILENAME
+                    } else {
+                        ATTACHED_FILENAME
+                    };
+                    match signer.sign_attached_file(path, output, self.armored) {
+                        Ok(_) => (),
+                        Err(e) => {
+                            self.error = Some(e);
+                        }
+                    }
+                }
+            }
+            // Odabrana datoteka je poruka s potpisom
+            SignMessage::VerifyAttached => {
+                if let Some(path) = &self.selected_file {
+                    let verifier = match self.verify_key() {
+                        Ok(verifier) => verifier,
+                        Err(e) => {
+                            self.error = Some(e);
                             return;
                         }
                     };
-                    self.file_verified = Some(verify);
+                    let verification = verifier.verify_attached_file(path, EXTRACTED_FILENAME);
+                    self.message_extracted = verification.is_valid();
+                    self.verification = Some(verification);
                 }
             }
         }
     }
 
-    pub fn view(&self) -> Element<SignMessage> {
+    fn signing_key(&self) -> Result<SigningKey> {
+        let passphrase = private_key_passphrase(None)?;
+        let mut signer =
+            SigningKey::from_file(None, passphrase.as_deref(), self.min_rsa_key_length)?;
+        signer.set_signature_padding(self.padding);
+        signer.set_digest(self.digest);
+        signer.set_mode(self.mode);
+        if self.attach_certificates {
+            signer.set_certificate_chain(load_certificate_chain(&read_file_to_buffer(
+                self.certificate_path(),
+            )?)?)?;
+        }
+        Ok(signer)
+    }
+
+    // Uz pouzdane korijenske certifikate javni kljuc se koristi samo ako je odabran
+    fn verify_key(&self) -> Result<VerifyKey> {
+        Ok(match (self.use_trusted_roots, &self.selected_public_key) {
+            (true, None) => VerifyKey::from_trust_store(TrustStore::open(None)?),
+            (use_trusted_roots, selected) => {
+                let mut verifier =
+                    VerifyKey::from_file(selected.as_deref(), self.min_rsa_key_length)?;
+                if use_trusted_roots {
+                    verifier.set_trust_store(TrustStore::open(None)?);
+                }
+                verifier
+            }
+        })
+    }
+
+    fn certificate_path(&self) -> &str {
+        self.selected_certificate
+            .as_deref()
+            .unwrap_or(ISSUED_CERTIFICATE_FILENAME)
+    }
+
+    fn add_trusted_root(&mut self) -> Result<()> {
+        let path = match open_file_dialog("Odabir pouzdanog korijenskog certifikata", "", None) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let certificate = load_certificate(&read_file_to_buffer(&path)?)?;
+        TrustStore::open(None)?.add_root(&certificate)?;
+        self.info = Some(format!(
+            "Certifikat {} je dodan u pouzdane korijenske certifikate",
+            name_to_string(certificate.subject_name())
+        ));
+        Ok(())
+    }
+
+    // Lista se koristi kod provjere lanca certifikata ako ju je potpisao izdavatelj certifikata
+    fn add_crl(&mut self) -> Result<()> {
+        let path = match open_file_dialog("Odabir liste opozvanih certifikata", "", None) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let crl = load_crl(&read_file_to_buffer(&path)?)?;
+        TrustStore::open(None)?.add_crl(&crl)?;
+        self.info = Some(format!(
+            "Dodana je lista opozvanih certifikata izdavatelja {} ({} opozvanih)",
+            name_to_string(crl.issuer_name()),
+            crl.revoked_certificates()?.len()
+        ));
+        Ok(())
+    }
+
+    pub fn view(&self) -> Element<'_, SignMessage> {
         let load_file_button = if let Some(path) = self.selected_file.as_ref() {
             widget::column![
                 text(format!("Datoteka: {}", path_to_filename(path))),
@@ -128,6 +331,24 @@ impl SignView {
                 .on_press(SignMessage::LoadFile(LoadFileType::Signature))]
             .spacing(5)
         };
+        let public_key_path = self
+            .selected_public_key
+            .as_deref()
+            .unwrap_or(PUBLIC_KEY_FILENAME);
+        let public_key = match public_key_id(public_key_path) {
+            _ if self.use_trusted_roots && self.selected_public_key.is_none() => {
+                "iz certifikata potpisnika".to_string()
+            }
+            Some(id) => format!("{}\n{}", path_to_filename(public_key_path), id),
+            None => path_to_filename(public_key_path),
+        };
+        let load_public_key = widget::column![
+            text(format!("Javni kljuc: {}", public_key)),
+            styled_button("Odabir javnog kljuca")
+                .on_press(SignMessage::LoadFile(LoadFileType::PublicKey))
+        ]
+        .spacing(5);
+
         let sign_button = if let Some(_sf) = self.selected_file.as_ref() {
             styled_button("Potpisi").on_press(SignMessage::Sign)
         } else {
@@ -142,21 +363,162 @@ impl SignView {
         } else {
             styled_button("Provjera potpisa")
         };
+        let (sign_attached_button, verify_attached_button) = if self.selected_file.is_some() {
+            (
+                styled_button("Potpisi s porukom").on_press(SignMessage::SignAttached),
+                styled_button("Provjeri i izdvoji poruku").on_press(SignMessage::VerifyAttached),
+            )
+        } else {
+            (
+                styled_button("Potpisi s porukom"),
+                styled_button("Provjeri i izdvoji poruku"),
+            )
+        };
         let mut column = styled_column(None);
 
         if let Some(e) = &self.error {
             column = column.push(styled_error(e));
         }
+        if let Some(info) = &self.info {
+            column = column.push(text(info));
+        }
+
+        let padding_picker = widget::column![
+            text("Nacin potpisivanja (RSA)"),
+            pick_list(
+                &SignaturePadding::ALL[..],
+                Some(self.padding),
+                SignMessage::SelectPadding
+            )
+        ]
+        .spacing(5);
+
+        let digest_picker = widget::column![
+            text("Algoritam sazetka"),
+            pick_list(
+                &HashAlgorithm::ALL[..],
+                Some(self.digest),
+                SignMessage::SelectDigest
+            )
+        ]
+        .spacing(5);
+
+        let mode_picker = widget::column![
+            text("Potpisivanje datoteke"),
+            pick_list(
+                &SignatureMode::ALL[..],
+                Some(self.mode),
+                SignMessage::SelectMode
+            )
+        ]
+        .spacing(5);
+
+        // Potpisuje se privatnim kljucem para ciji je javni kljuc u PUBLIC_KEY_FILENAME
+        if let Some(id) = public_key_id(PUBLIC_KEY_FILENAME) {
+            column = column.push(text(format!("Kljuc za potpisivanje: {}", id)));
+        }
 
         column = column
-            .push(styled_row().push(load_file_button).push(load_signature))
-            .push(styled_row().push(sign_button).push(verify_button));
+            .push(
+                styled_row()
+                    .push(load_file_button)
+                    .push(load_signature)
+                    .push(load_public_key)
+                    .push(padding_picker)
+                    .push(digest_picker)
+                    .push(mode_picker),
+            )
+            .push(styled_row().push(sign_button).push(verify_button))
+            .push(text(format!(
+                "Potpis se sprema u datoteku {}, a potpis samog sadrzaja datoteke \
+                 (za provjeru naredbom openssl) u {}",
+                SIGNATURE_FILENAME, DETACHED_SIGNATURE_FILENAME
+            )))
+            .push(
+                styled_row()
+                    .push(sign_attached_button)
+                    .push(verify_attached_button),
+            )
+            .push(checkbox(
+                "ASCII zapis poruke s potpisom (Base64)",
+                self.armored,
+                SignMessage::Armored,
+            ));
+
+        let trusted_roots = match TrustStore::open(None) {
+            Ok(trust_store) => format!(
+                "Pouzdanih korijenskih certifikata: {}\nListi opozvanih certifikata: {}",
+                trust_store.roots().len(),
+                trust_store.crls().len()
+            ),
+            Err(e) => e.to_string(),
+        };
+        column = column.push(
+            styled_row()
+                .push(
+                    widget::column![
+                        checkbox(
+                            "Prilozi potpisu lanac certifikata potpisnika",
+                            self.attach_certificates,
+                            SignMessage::AttachCertificates,
+                        ),
+                        text(format!(
+                            "Lanac certifikata: {}",
+                            path_to_filename(self.certificate_path())
+                        )),
+                        styled_button("Odabir certifikata potpisnika")
+                            .on_press(SignMessage::LoadFile(LoadFileType::Certificate))
+                    ]
+                    .spacing(5),
+                )
+                .push(
+                    widget::column![
+                        checkbox(
+                            "Provjera lancem certifikata do pouzdanog korijena",
+                            self.use_trusted_roots,
+                            SignMessage::UseTrustedRoots,
+                        ),
+                        text(trusted_roots),
+                        styled_button("Dodaj pouzdani korijenski certifikat")
+                            .on_press(SignMessage::AddTrustedRoot),
+                        styled_button("Dodaj listu opozvanih certifikata (CRL)")
+                            .on_press(SignMessage::AddCrl)
+                    ]
+                    .spacing(5),
+                ),
+        );
 
-        if let Some(hash) = self.file_verified.as_ref() {
-            if *hash {
-                column = column.push(text("Potpis valjan.").style(GREEN));
+        if let Some(verification) = &self.verification {
+            let valid = verification.is_valid();
+            column = column.push(text(verification.outcome.to_string()).style(if valid {
+                GREEN
             } else {
-                column = column.push(text("Potpis nije valjan.").style(RED));
+                RED
+            }));
+            if let Some(signer) = &verification.signer {
+                column = column.push(text(format!("Potpisnik: {}", signer)).style(GREEN));
+            }
+            if self.message_extracted {
+                column = column.push(text(format!(
+                    "Poruka je izdvojena u datoteku {}",
+                    EXTRACTED_FILENAME
+                )));
+            }
+            if let Some(info) = &verification.info {
+                // Podaci iz nevaljanog potpisa nisu potvrdeni potpisom
+                let title = if valid {
+                    "Podaci o potpisu:"
+                } else {
+                    "Podaci o potpisu (nepotvrdeni):"
+                };
+                column = column.push(text(format!("{}\n{}", title, info)));
+                if info.metadata.is_none()
+                    && matches!(verification.outcome, VerificationOutcome::FileChanged)
+                {
+                    column = column.push(text(
+                        "Potpis ne sadrzi otisak kljuca potpisnika, pa je moguce i da je kreiran drugim kljucem.",
+                    ));
+                }
             }
         }
 
@@ -1,7 +1,7 @@
 use iced::{
     alignment,
     widget::{self, text, Button, Column, Row},
-    Color,
+    Color, Length,
 };
 
 pub const RED: Color = Color {
@@ -18,7 +18,7 @@ pub const GREEN: Color = Color {
     a: 1.0,
 };
 
-pub fn styled_column<Message: Clone>(title: Option<&str>) -> Column<Message> {
+pub fn styled_column<Message: Clone>(title: Option<&str>) -> Column<'_, Message> {
     let mut column = widget::column![];
     if let Some(title) = title {
         column = column.push(text(title).size(50));
@@ -30,13 +30,31 @@ pub fn styled_row<'a, Message: Clone>() -> Row<'a, Message> {
     widget::row![].spacing(20)
 }
 
-pub fn styled_button<Message: Clone>(label: &str) -> Button<Message> {
+pub fn styled_button<Message: Clone>(label: &str) -> Button<'_, Message> {
     widget::button(text(label).horizontal_alignment(alignment::Horizontal::Center))
         .padding(10)
         .width(iced::Length::Units(150))
 }
 
-pub fn styled_error<Message: Clone>(error: &anyhow::Error) -> Column<Message> {
+// Svaki znak je u celiji iste sirine pa je slika poravnata i bez fonta fiksne sirine
+pub fn styled_randomart<'a, Message: Clone + 'a>(lines: &[String]) -> Column<'a, Message> {
+    let mut column = widget::column![];
+    for line in lines {
+        let mut row = widget::row![];
+        for symbol in line.chars() {
+            row = row.push(
+                text(symbol)
+                    .size(16)
+                    .width(Length::Units(10))
+                    .horizontal_alignment(alignment::Horizontal::Center),
+            );
+        }
+        column = column.push(row);
+    }
+    column
+}
+
+pub fn styled_error<Message: Clone>(error: &anyhow::Error) -> Column<'_, Message> {
     let text = text(error.to_string()).style(RED);
     widget::column![text].spacing(5)
 }
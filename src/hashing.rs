@@ -0,0 +1,162 @@
+use std::{fmt, fs::File, io::Read};
+
+use anyhow::{anyhow, Result};
+use openssl::hash::{hash, Hasher, MessageDigest};
+
+use crate::file_manip::{read_chunks, read_file_to_buffer, read_text_file};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+    Sha512_256,
+    Sha3_256,
+    Sha3_512,
+    Blake2b512,
+    Blake2s256,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [HashAlgorithm; 9] = [
+        HashAlgorithm::Sha224,
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Sha384,
+        HashAlgorithm::Sha512,
+        HashAlgorithm::Sha512_256,
+        HashAlgorithm::Sha3_256,
+        HashAlgorithm::Sha3_512,
+        HashAlgorithm::Blake2b512,
+        HashAlgorithm::Blake2s256,
+    ];
+
+    pub fn message_digest(&self) -> Result<MessageDigest> {
+        match self {
+            HashAlgorithm::Sha224 => Ok(MessageDigest::sha224()),
+            HashAlgorithm::Sha256 => Ok(MessageDigest::sha256()),
+            HashAlgorithm::Sha384 => Ok(MessageDigest::sha384()),
+            HashAlgorithm::Sha512 => Ok(MessageDigest::sha512()),
+            HashAlgorithm::Sha3_256 => Ok(MessageDigest::sha3_256()),
+            HashAlgorithm::Sha3_512 => Ok(MessageDigest::sha3_512()),
+            // Nema posebnog konstruktora u openssl paketu, dohvacaju se po imenu
+            HashAlgorithm::Sha512_256 | HashAlgorithm::Blake2b512 | HashAlgorithm::Blake2s256 => {
+                MessageDigest::from_name(self.name())
+                    .ok_or_else(|| anyhow!("Algoritam {} nije podrzan u OpenSSL-u", self))
+            }
+        }
+    }
+
+    // Ime koje se zapisuje uz sazetak (isto kao kod `openssl dgst`)
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha224 => "SHA224",
+            HashAlgorithm::Sha256 => "SHA256",
+            HashAlgorithm::Sha384 => "SHA384",
+            HashAlgorithm::Sha512 => "SHA512",
+            HashAlgorithm::Sha512_256 => "SHA512-256",
+            HashAlgorithm::Sha3_256 => "SHA3-256",
+            HashAlgorithm::Sha3_512 => "SHA3-512",
+            HashAlgorithm::Blake2b512 => "BLAKE2b512",
+            HashAlgorithm::Blake2s256 => "BLAKE2s256",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        HashAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow!("Nepoznat algoritam sazetka ({})", name))
+    }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            HashAlgorithm::Sha224 => 1,
+            HashAlgorithm::Sha256 => 2,
+            HashAlgorithm::Sha384 => 3,
+            HashAlgorithm::Sha512 => 4,
+            HashAlgorithm::Sha512_256 => 5,
+            HashAlgorithm::Sha3_256 => 6,
+            HashAlgorithm::Sha3_512 => 7,
+            HashAlgorithm::Blake2b512 => 8,
+            HashAlgorithm::Blake2s256 => 9,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Result<Self> {
+        HashAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.id() == id)
+            .ok_or_else(|| anyhow!("Nepoznat algoritam sazetka ({})", id))
+    }
+
+    pub fn hash(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(hash(self.message_digest()?, data)?.to_vec())
+    }
+
+    pub fn hash_reader<R: Read>(&self, reader: &mut R) -> Result<Vec<u8>> {
+        let mut hasher = Hasher::new(self.message_digest()?)?;
+        read_chunks(reader, |chunk| Ok(hasher.update(chunk)?))?;
+        Ok(hasher.finish()?.to_vec())
+    }
+
+    pub fn hash_file(&self, filename: &str) -> Result<Vec<u8>> {
+        self.hash_reader(&mut File::open(filename)?)
+    }
+
+    // Sazetak teksta neovisan o zavrsecima redaka (CR LF, CR ili LF)
+    pub fn hash_text_file(&self, filename: &str) -> Result<Vec<u8>> {
+        self.hash(&read_text_file(filename)?)
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashAlgorithm::Sha224 => write!(f, "SHA-224"),
+            HashAlgorithm::Sha256 => write!(f, "SHA-256"),
+            HashAlgorithm::Sha384 => write!(f, "SHA-384"),
+            HashAlgorithm::Sha512 => write!(f, "SHA-512"),
+            HashAlgorithm::Sha512_256 => write!(f, "SHA-512/256"),
+            HashAlgorithm::Sha3_256 => write!(f, "SHA3-256"),
+            HashAlgorithm::Sha3_512 => write!(f, "SHA3-512"),
+            HashAlgorithm::Blake2b512 => write!(f, "BLAKE2b-512"),
+            HashAlgorithm::Blake2s256 => write!(f, "BLAKE2s-256"),
+        }
+    }
+}
+
+// Zapis sazetka s imenom algoritma: "SHA3-256 (datoteka) = <hex>"
+pub fn format_digest(algorithm: HashAlgorithm, filename: &str, digest: &[u8]) -> String {
+    format!(
+        "{} ({}) = {}",
+        algorithm.name(),
+        filename,
+        hex::encode(digest)
+    )
+}
+
+pub fn parse_digest(line: &str) -> Result<(HashAlgorithm, Vec<u8>)> {
+    let line = line.trim();
+    let (name, rest) = line
+        .split_once(" (")
+        .ok_or_else(|| anyhow!("Neispravan format zapisa sazetka"))?;
+    let (_, digest) = rest
+        .rsplit_once(") = ")
+        .ok_or_else(|| anyhow!("Neispravan format zapisa sazetka"))?;
+    let digest = hex::decode(digest).map_err(|_| anyhow!("Sazetak nije ispravan hex zapis"))?;
+    Ok((HashAlgorithm::from_name(name)?, digest))
+}
+
+// Algoritam se cita iz zapisa sazetka pa se sazetak datoteke ponovno izracunava istim algoritmom
+pub fn verify_digest_file(filename: &str, digest_filename: &str, text_mode: bool) -> Result<bool> {
+    let record = read_file_to_buffer(digest_filename)?;
+    let record = String::from_utf8(record).map_err(|_| anyhow!("Neispravan zapis sazetka"))?;
+    let (algorithm, digest) = parse_digest(&record)?;
+    let file_digest = if text_mode {
+        algorithm.hash_text_file(filename)?
+    } else {
+        algorithm.hash_file(filename)?
+    };
+    Ok(file_digest == digest)
+}
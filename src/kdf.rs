@@ -0,0 +1,298 @@
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use openssl::{
+    hash::MessageDigest,
+    pkcs5::{pbkdf2_hmac, scrypt},
+};
+
+use crate::{
+    file_container::{read_length_prefixed, write_length_prefixed},
+    keygen::Keygen,
+};
+
+pub const DERIVED_KEY_LENGTH: usize = 32;
+// Parametri iz datoteke se ogranicavaju kako izmijenjena datoteka ne bi zauzela svu memoriju
+const MAX_MEMORY: u64 = 1024 * 1024 * 1024;
+const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+const MAX_ARGON2_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KdfAlgorithm {
+    Pbkdf2Sha256,
+    Scrypt,
+    Argon2id,
+}
+
+impl KdfAlgorithm {
+    pub const ALL: [KdfAlgorithm; 3] = [
+        KdfAlgorithm::Pbkdf2Sha256,
+        KdfAlgorithm::Scrypt,
+        KdfAlgorithm::Argon2id,
+    ];
+}
+
+impl fmt::Display for KdfAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KdfAlgorithm::Pbkdf2Sha256 => write!(f, "PBKDF2-HMAC-SHA256"),
+            KdfAlgorithm::Scrypt => write!(f, "scrypt"),
+            KdfAlgorithm::Argon2id => write!(f, "Argon2id"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KdfStrength {
+    Basic,
+    Increased,
+    Maximum,
+}
+
+impl KdfStrength {
+    pub const ALL: [KdfStrength; 3] = [
+        KdfStrength::Basic,
+        KdfStrength::Increased,
+        KdfStrength::Maximum,
+    ];
+}
+
+impl fmt::Display for KdfStrength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KdfStrength::Basic => write!(f, "Osnovna"),
+            KdfStrength::Increased => write!(f, "Pojacana"),
+            KdfStrength::Maximum => write!(f, "Najjaca"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kdf {
+    Pbkdf2Sha256 {
+        iterations: u32,
+    },
+    // N = 2^log_n
+    Scrypt {
+        log_n: u32,
+        r: u32,
+        p: u32,
+    },
+    Argon2id {
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+impl Kdf {
+    // Osnovna jacina odgovara preporukama OWASP-a, ostale trose vise vremena i memorije
+    pub fn new(algorithm: KdfAlgorithm, strength: KdfStrength) -> Self {
+        let level = match strength {
+            KdfStrength::Basic => 0,
+            KdfStrength::Increased => 1,
+            KdfStrength::Maximum => 2,
+        };
+        match algorithm {
+            KdfAlgorithm::Pbkdf2Sha256 => Kdf::Pbkdf2Sha256 {
+                iterations: 600_000 << level,
+            },
+            KdfAlgorithm::Scrypt => Kdf::Scrypt {
+                log_n: 15 + level,
+                r: 8,
+                p: 1,
+            },
+            KdfAlgorithm::Argon2id => Kdf::Argon2id {
+                memory_kib: [19 * 1024, 64 * 1024, 256 * 1024][level as usize],
+                iterations: [2, 3, 4][level as usize],
+                parallelism: [1, 4, 4][level as usize],
+            },
+        }
+    }
+
+    pub fn derive_key(&self, password: &[u8], salt: &[u8]) -> Result<Vec<u8>> {
+        self.check_limits()?;
+        let mut key = vec![0; DERIVED_KEY_LENGTH];
+        match *self {
+            Kdf::Pbkdf2Sha256 { iterations } => pbkdf2_hmac(
+                password,
+                salt,
+                iterations as usize,
+                MessageDigest::sha256(),
+                &mut key,
+            )?,
+            Kdf::Scrypt { log_n, r, p } => scrypt(
+                password,
+                salt,
+                1 << log_n,
+                r.into(),
+                p.into(),
+                self.memory(),
+                &mut key,
+            )?,
+            Kdf::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                let params = Params::new(
+                    memory_kib,
+                    iterations,
+                    parallelism,
+                    Some(DERIVED_KEY_LENGTH),
+                )?;
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password_into(password, salt, &mut key)?;
+            }
+        }
+        Ok(key)
+    }
+
+    // Memorija potrebna za izvodenje kljuca u bajtovima (kao u EVP_PBE_scrypt)
+    fn memory(&self) -> u64 {
+        match *self {
+            Kdf::Pbkdf2Sha256 { .. } => 0,
+            Kdf::Scrypt { log_n, r, p } => {
+                let block = 128 * u64::from(r);
+                block * ((1u64 << log_n) + 2) + block * u64::from(p)
+            }
+            Kdf::Argon2id { memory_kib, .. } => u64::from(memory_kib) * 1024,
+        }
+    }
+
+    fn check_limits(&self) -> Result<()> {
+        let within_limits = match *self {
+            Kdf::Pbkdf2Sha256 { iterations } => (1..=MAX_PBKDF2_ITERATIONS).contains(&iterations),
+            Kdf::Scrypt { log_n, r, p } => {
+                (1..40).contains(&log_n)
+                    && r > 0
+                    && (1..=MAX_PARALLELISM).contains(&p)
+                    && self.memory() <= MAX_MEMORY
+            }
+            Kdf::Argon2id {
+                iterations,
+                parallelism,
+                ..
+            } => {
+                (1..=MAX_ARGON2_ITERATIONS).contains(&iterations)
+                    && (1..=MAX_PARALLELISM).contains(&parallelism)
+                    && self.memory() <= MAX_MEMORY
+            }
+        };
+        if !within_limits {
+            return Err(anyhow!(
+                "Nedopusteni parametri za izvodenje kljuca ({})",
+                self
+            ));
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            Kdf::Pbkdf2Sha256 { .. } => 1,
+            Kdf::Scrypt { .. } => 2,
+            Kdf::Argon2id { .. } => 3,
+        }
+    }
+
+    fn values(&self) -> [u32; 3] {
+        match *self {
+            Kdf::Pbkdf2Sha256 { iterations } => [iterations, 0, 0],
+            Kdf::Scrypt { log_n, r, p } => [log_n, r, p],
+            Kdf::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => [memory_kib, iterations, parallelism],
+        }
+    }
+
+    fn from_values(id: u8, values: [u32; 3]) -> Result<Self> {
+        let [a, b, c] = values;
+        match id {
+            1 => Ok(Kdf::Pbkdf2Sha256 { iterations: a }),
+            2 => Ok(Kdf::Scrypt {
+                log_n: a,
+                r: b,
+                p: c,
+            }),
+            3 => Ok(Kdf::Argon2id {
+                memory_kib: a,
+                iterations: b,
+                parallelism: c,
+            }),
+            _ => Err(anyhow!("Nepoznat algoritam za izvodenje kljuca ({})", id)),
+        }
+    }
+}
+
+impl fmt::Display for Kdf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kdf::Pbkdf2Sha256 { iterations } => {
+                write!(f, "PBKDF2-HMAC-SHA256, {} iteracija", iterations)
+            }
+            Kdf::Scrypt { log_n, r, p } => write!(f, "scrypt, N=2^{}, r={}, p={}", log_n, r, p),
+            Kdf::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => write!(
+                f,
+                "Argon2id, m={} KiB, t={}, p={}",
+                memory_kib, iterations, parallelism
+            ),
+        }
+    }
+}
+
+// Sve sto je uz lozinku potrebno za ponovno izvodenje kljuca
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KdfParams {
+    pub kdf: Kdf,
+    pub salt: Vec<u8>,
+}
+
+impl KdfParams {
+    pub fn new(kdf: Kdf) -> Self {
+        Self {
+            kdf,
+            salt: Keygen::default().generate_128bit_key().to_vec(),
+        }
+    }
+
+    pub fn derive_key(&self, password: &str) -> Result<Vec<u8>> {
+        self.kdf.derive_key(password.as_bytes(), &self.salt)
+    }
+
+    // [algoritam][3 x u32 BE][sol]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![self.kdf.id()];
+        for value in self.kdf.values() {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        write_length_prefixed(&mut out, &self.salt);
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let malformed = || anyhow!("Neispravan zapis parametara za izvodenje kljuca");
+        if data.len() < 13 {
+            return Err(malformed());
+        }
+        let mut values = [0; 3];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = u32::from_be_bytes(data[1 + 4 * i..5 + 4 * i].try_into()?);
+        }
+        let kdf = Kdf::from_values(data[0], values)?;
+        kdf.check_limits()?;
+        let (salt, _) = read_length_prefixed(&data[13..]).ok_or_else(malformed)?;
+        Ok(Self {
+            kdf,
+            salt: salt.to_vec(),
+        })
+    }
+}
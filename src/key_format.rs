@@ -0,0 +1,564 @@
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use openssl::{
+    base64::{decode_block, encode_block},
+    bn::{BigNum, BigNumContext, BigNumRef},
+    ec::{EcGroup, EcGroupRef, EcKey, EcPoint, PointConversionForm},
+    nid::Nid,
+    pkey::{HasPublic, Id, PKey, Private, Public},
+    rsa::Rsa,
+};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    file_container::{read_length_prefixed, write_length_prefixed},
+    fingerprint::{key_fingerprint, short_key_id},
+    private_key::{
+        encrypted_private_key_to_der, pem_to_der, private_key_from_der, private_key_from_pem,
+        private_key_to_pem,
+    },
+    signing::KeyAlgorithm,
+};
+
+// Komentaru se dodaje ID kljuca
+const OPENSSH_COMMENT: &str = "os2_projekt";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyFormat {
+    Pem,
+    PemPkcs1,
+    Der,
+    DerPkcs1,
+    Jwk,
+    OpenSsh,
+}
+
+impl KeyFormat {
+    pub const ALL: [KeyFormat; 6] = [
+        KeyFormat::Pem,
+        KeyFormat::PemPkcs1,
+        KeyFormat::Der,
+        KeyFormat::DerPkcs1,
+        KeyFormat::Jwk,
+        KeyFormat::OpenSsh,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            KeyFormat::Pem | KeyFormat::PemPkcs1 => "pem",
+            KeyFormat::Der | KeyFormat::DerPkcs1 => "der",
+            KeyFormat::Jwk => "jwk",
+            KeyFormat::OpenSsh => "pub",
+        }
+    }
+
+    // Samo se PKCS#8 izvozi sifriran lozinkom
+    pub fn supports_encryption(&self) -> bool {
+        matches!(self, KeyFormat::Pem | KeyFormat::Der)
+    }
+
+    // Prepoznaje se po sadrzaju, a DER po tome moze li se procitati kao PKCS#1
+    pub fn detect(data: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(data);
+        let text = text.trim_start();
+        if text.starts_with('{') {
+            KeyFormat::Jwk
+        } else if text.starts_with("ssh-") || text.starts_with("ecdsa-sha2-") {
+            KeyFormat::OpenSsh
+        } else if text.starts_with("-----BEGIN RSA ") {
+            KeyFormat::PemPkcs1
+        } else if text.starts_with("-----BEGIN") {
+            KeyFormat::Pem
+        } else if Rsa::public_key_from_der_pkcs1(data).is_ok()
+            || Rsa::private_key_from_der(data).is_ok()
+        {
+            KeyFormat::DerPkcs1
+        } else {
+            KeyFormat::Der
+        }
+    }
+}
+
+impl fmt::Display for KeyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyFormat::Pem => write!(f, "PEM (PKCS#8 / SPKI)"),
+            KeyFormat::PemPkcs1 => write!(f, "PEM (PKCS#1)"),
+            KeyFormat::Der => write!(f, "DER (PKCS#8 / SPKI)"),
+            KeyFormat::DerPkcs1 => write!(f, "DER (PKCS#1)"),
+            KeyFormat::Jwk => write!(f, "JSON Web Key"),
+            KeyFormat::OpenSsh => write!(f, "OpenSSH"),
+        }
+    }
+}
+
+pub fn export_public_key<T: HasPublic>(pkey: &PKey<T>, format: KeyFormat) -> Result<Vec<u8>> {
+    match format {
+        KeyFormat::Pem => Ok(pkey.public_key_to_pem()?),
+        KeyFormat::Der => Ok(pkey.public_key_to_der()?),
+        KeyFormat::PemPkcs1 => Ok(pkcs1_rsa(pkey)?.public_key_to_pem_pkcs1()?),
+        KeyFormat::DerPkcs1 => Ok(pkcs1_rsa(pkey)?.public_key_to_der_pkcs1()?),
+        KeyFormat::Jwk => Ok(serde_json::to_vec_pretty(&public_jwk(pkey)?)?),
+        KeyFormat::OpenSsh => Ok(format!("{}\n", openssh_public_key(pkey)?).into_bytes()),
+    }
+}
+
+// Lozinkom se moze zastititi samo PKCS#8, ostali formati se izvoze nezasticeni
+pub fn export_private_key(
+    pkey: &PKey<Private>,
+    format: KeyFormat,
+    passphrase: Option<&str>,
+) -> Result<Vec<u8>> {
+    if passphrase.is_some() && !format.supports_encryption() {
+        return Err(anyhow!(
+            "Format {} ne podrzava zastitu privatnog kljuca lozinkom",
+            format
+        ));
+    }
+    match format {
+        KeyFormat::Pem => private_key_to_pem(pkey, passphrase),
+        KeyFormat::Der => match passphrase {
+            Some(passphrase) => encrypted_private_key_to_der(pkey, passphrase),
+            None => pem_to_der(&pkey.private_key_to_pem_pkcs8()?),
+        },
+        KeyFormat::PemPkcs1 => Ok(pkcs1_rsa(pkey)?.private_key_to_pem()?),
+        KeyFormat::DerPkcs1 => Ok(pkcs1_rsa(pkey)?.private_key_to_der()?),
+        KeyFormat::Jwk => {
+            let mut jwk = public_jwk(pkey)?;
+            jwk.extend(private_jwk_members(pkey)?);
+            Ok(serde_json::to_vec_pretty(&jwk)?)
+        }
+        KeyFormat::OpenSsh => Err(anyhow!("OpenSSH format je podrzan samo za javne kljuceve")),
+    }
+}
+
+// Iz privatnog kljuca u bilo kojem formatu uzima se javni dio
+pub fn import_public_key(data: &[u8]) -> Result<PKey<Public>> {
+    let pkey = match KeyFormat::detect(data) {
+        KeyFormat::Jwk => jwk_to_public_key(&parse_jwk(data)?),
+        KeyFormat::OpenSsh => openssh_to_public_key(data),
+        KeyFormat::Pem | KeyFormat::PemPkcs1 => PKey::public_key_from_pem(data)
+            .or_else(|_| Rsa::public_key_from_pem_pkcs1(data).and_then(PKey::from_rsa))
+            .or_else(|_| PKey::private_key_from_pem(data).and_then(|pkey| public_part(&pkey)))
+            .map_err(anyhow::Error::from),
+        KeyFormat::Der | KeyFormat::DerPkcs1 => PKey::public_key_from_der(data)
+            .or_else(|_| Rsa::public_key_from_der_pkcs1(data).and_then(PKey::from_rsa))
+            .or_else(|_| PKey::private_key_from_der(data).and_then(|pkey| public_part(&pkey)))
+            .map_err(anyhow::Error::from),
+    };
+    pkey.map_err(|_| anyhow!("Neispravan format javnog kljuca"))
+}
+
+// Lozinka je potrebna samo za PEM zasticen lozinkom
+pub fn import_private_key(data: &[u8], passphrase: Option<&str>) -> Result<PKey<Private>> {
+    match KeyFormat::detect(data) {
+        KeyFormat::Jwk => jwk_to_private_key(&parse_jwk(data)?),
+        KeyFormat::OpenSsh => Err(anyhow!("OpenSSH format je podrzan samo za javne kljuceve")),
+        KeyFormat::Pem | KeyFormat::PemPkcs1 => private_key_from_pem(data, passphrase),
+        KeyFormat::Der | KeyFormat::DerPkcs1 => private_key_from_der(data, passphrase),
+    }
+}
+
+fn public_part<T: HasPublic>(pkey: &PKey<T>) -> Result<PKey<Public>, openssl::error::ErrorStack> {
+    PKey::public_key_from_der(&pkey.public_key_to_der()?)
+}
+
+fn pkcs1_rsa<T>(pkey: &PKey<T>) -> Result<Rsa<T>> {
+    pkey.rsa()
+        .map_err(|_| anyhow!("PKCS#1 format je podrzan samo za RSA kljuceve"))
+}
+
+// Ime krivulje u JWK i OpenSSH zapisu
+fn ec_curve_names(algorithm: KeyAlgorithm) -> (&'static str, &'static str) {
+    match algorithm {
+        KeyAlgorithm::EcdsaP384 => ("P-384", "nistp384"),
+        _ => ("P-256", "nistp256"),
+    }
+}
+
+fn ec_group_by_name(name: &str) -> Result<EcGroup> {
+    let curve = match name {
+        "P-256" | "nistp256" => Nid::X9_62_PRIME256V1,
+        "P-384" | "nistp384" => Nid::SECP384R1,
+        _ => return Err(anyhow!("Nepodrzana elipticna krivulja ({})", name)),
+    };
+    Ok(EcGroup::from_curve_name(curve)?)
+}
+
+// JSON Web Key (RFC 7517, 7518 i 8037)
+fn public_jwk<T: HasPublic>(pkey: &PKey<T>) -> Result<Map<String, Value>> {
+    let algorithm = KeyAlgorithm::of(pkey)?;
+    let jwk = match algorithm {
+        KeyAlgorithm::Rsa => {
+            let rsa = pkey.rsa()?;
+            json!({
+                "kty": "RSA",
+                "n": base64url(&rsa.n().to_vec()),
+                "e": base64url(&rsa.e().to_vec())
+            })
+        }
+        KeyAlgorithm::Ed25519 => {
+            json!({ "kty": "OKP", "crv": "Ed25519", "x": base64url(&pkey.raw_public_key()?) })
+        }
+        KeyAlgorithm::EcdsaP256 | KeyAlgorithm::EcdsaP384 => {
+            let (crv, _) = ec_curve_names(algorithm);
+            let ec_key = pkey.ec_key()?;
+            let (x, y) = affine_coordinates(&ec_key)?;
+            json!({ "kty": "EC", "crv": crv, "x": base64url(&x), "y": base64url(&y) })
+        }
+    };
+    let mut jwk = match jwk {
+        Value::Object(map) => map,
+        _ => return Err(anyhow!("Neispravan JWK zapis")),
+    };
+    let kid = short_key_id(&key_fingerprint(pkey)?);
+    jwk.insert("kid".to_string(), Value::String(kid));
+    Ok(jwk)
+}
+
+fn private_jwk_members(pkey: &PKey<Private>) -> Result<Map<String, Value>> {
+    let mut members = Map::new();
+    let mut add = |name: &str, value: &[u8]| {
+        members.insert(name.to_string(), Value::String(base64url(value)));
+    };
+    match KeyAlgorithm::of(pkey)? {
+        KeyAlgorithm::Rsa => {
+            let rsa = pkey.rsa()?;
+            add("d", &rsa.d().to_vec());
+            let primes = [
+                ("p", rsa.p()),
+                ("q", rsa.q()),
+                ("dp", rsa.dmp1()),
+                ("dq", rsa.dmq1()),
+                ("qi", rsa.iqmp()),
+            ];
+            for (name, value) in primes {
+                let value = value.ok_or_else(|| anyhow!("RSA kljucu nedostaju parametri"))?;
+                add(name, &value.to_vec());
+            }
+        }
+        KeyAlgorithm::Ed25519 => add("d", &pkey.raw_private_key()?),
+        KeyAlgorithm::EcdsaP256 | KeyAlgorithm::EcdsaP384 => {
+            let ec_key = pkey.ec_key()?;
+            let length = field_length(ec_key.group());
+            add("d", &ec_key.private_key().to_vec_padded(length)?);
+        }
+    }
+    Ok(members)
+}
+
+fn parse_jwk(data: &[u8]) -> Result<Map<String, Value>> {
+    match serde_json::from_slice(data) {
+        Ok(Value::Object(map)) => Ok(map),
+        _ => Err(anyhow!("Neispravan JWK zapis")),
+    }
+}
+
+fn jwk_member(jwk: &Map<String, Value>, name: &str) -> Result<Vec<u8>> {
+    let value = jwk
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("JWK zapisu nedostaje clan \"{}\"", name))?;
+    from_base64url(value)
+}
+
+fn jwk_number(jwk: &Map<String, Value>, name: &str) -> Result<BigNum> {
+    Ok(BigNum::from_slice(&jwk_member(jwk, name)?)?)
+}
+
+fn jwk_ec_key(jwk: &Map<String, Value>) -> Result<EcKey<Public>> {
+    let crv = jwk.get("crv").and_then(Value::as_str).unwrap_or_default();
+    let group = ec_group_by_name(crv)?;
+    let (x, y) = (jwk_number(jwk, "x")?, jwk_number(jwk, "y")?);
+    Ok(EcKey::from_public_key_affine_coordinates(&group, &x, &y)?)
+}
+
+fn jwk_key_type(jwk: &Map<String, Value>) -> Result<&str> {
+    let kty = jwk.get("kty").and_then(Value::as_str).unwrap_or_default();
+    match kty {
+        "RSA" | "EC" => Ok(kty),
+        "OKP" if jwk.get("crv").and_then(Value::as_str) == Some("Ed25519") => Ok(kty),
+        _ => Err(anyhow!("Nepodrzana vrsta JWK kljuca ({})", kty)),
+    }
+}
+
+fn jwk_to_public_key(jwk: &Map<String, Value>) -> Result<PKey<Public>> {
+    match jwk_key_type(jwk)? {
+        "RSA" => Ok(PKey::from_rsa(Rsa::from_public_components(
+            jwk_number(jwk, "n")?,
+            jwk_number(jwk, "e")?,
+        )?)?),
+        "EC" => Ok(PKey::from_ec_key(jwk_ec_key(jwk)?)?),
+        _ => Ok(PKey::public_key_from_raw_bytes(
+            &jwk_member(jwk, "x")?,
+            Id::ED25519,
+        )?),
+    }
+}
+
+fn jwk_to_private_key(jwk: &Map<String, Value>) -> Result<PKey<Private>> {
+    if !jwk.contains_key("d") {
+        return Err(anyhow!("JWK zapis ne sadrzi privatni kljuc"));
+    }
+    match jwk_key_type(jwk)? {
+        "RSA" => Ok(PKey::from_rsa(Rsa::from_private_components(
+            jwk_number(jwk, "n")?,
+            jwk_number(jwk, "e")?,
+            jwk_number(jwk, "d")?,
+            jwk_number(jwk, "p")?,
+            jwk_number(jwk, "q")?,
+            jwk_number(jwk, "dp")?,
+            jwk_number(jwk, "dq")?,
+            jwk_number(jwk, "qi")?,
+        )?)?),
+        "EC" => {
+            let public = jwk_ec_key(jwk)?;
+            let d = jwk_number(jwk, "d")?;
+            let ec_key = EcKey::from_private_components(public.group(), &d, public.public_key())?;
+            ec_key.check_key()?;
+            Ok(PKey::from_ec_key(ec_key)?)
+        }
+        _ => Ok(PKey::private_key_from_raw_bytes(
+            &jwk_member(jwk, "d")?,
+            Id::ED25519,
+        )?),
+    }
+}
+
+fn field_length(group: &EcGroupRef) -> i32 {
+    (group.degree() as i32 + 7) / 8
+}
+
+fn affine_coordinates<T: HasPublic>(ec_key: &EcKey<T>) -> Result<(Vec<u8>, Vec<u8>)> {
+    let group = ec_key.group();
+    let length = field_length(group);
+    let (mut x, mut y) = (BigNum::new()?, BigNum::new()?);
+    let mut context = BigNumContext::new()?;
+    ec_key
+        .public_key()
+        .affine_coordinates(group, &mut x, &mut y, &mut context)?;
+    Ok((x.to_vec_padded(length)?, y.to_vec_padded(length)?))
+}
+
+// OpenSSH javni kljuc (RFC 4253, 5656 i 8709): "<vrsta> <Base64 zapis> <komentar>"
+fn openssh_public_key<T: HasPublic>(pkey: &PKey<T>) -> Result<String> {
+    let algorithm = KeyAlgorithm::of(pkey)?;
+    let mut blob = Vec::new();
+    let key_type = match algorithm {
+        KeyAlgorithm::Rsa => {
+            let rsa = pkey.rsa()?;
+            write_length_prefixed(&mut blob, b"ssh-rsa");
+            write_length_prefixed(&mut blob, &mpint(rsa.e()));
+            write_length_prefixed(&mut blob, &mpint(rsa.n()));
+            "ssh-rsa".to_string()
+        }
+        KeyAlgorithm::Ed25519 => {
+            write_length_prefixed(&mut blob, b"ssh-ed25519");
+            write_length_prefixed(&mut blob, &pkey.raw_public_key()?);
+            "ssh-ed25519".to_string()
+        }
+        KeyAlgorithm::EcdsaP256 | KeyAlgorithm::EcdsaP384 => {
+            let (_, curve) = ec_curve_names(algorithm);
+            let key_type = format!("ecdsa-sha2-{}", curve);
+            let ec_key = pkey.ec_key()?;
+            let mut context = BigNumContext::new()?;
+            let point = ec_key.public_key().to_bytes(
+                ec_key.group(),
+                PointConversionForm::UNCOMPRESSED,
+                &mut context,
+            )?;
+            write_length_prefixed(&mut blob, key_type.as_bytes());
+            write_length_prefixed(&mut blob, curve.as_bytes());
+            write_length_prefixed(&mut blob, &point);
+            key_type
+        }
+    };
+    Ok(format!(
+        "{} {} {}-{}",
+        key_type,
+        encode_block(&blob),
+        OPENSSH_COMMENT,
+        short_key_id(&key_fingerprint(pkey)?)
+    ))
+}
+
+fn openssh_to_public_key(data: &[u8]) -> Result<PKey<Public>> {
+    let malformed = || anyhow!("Neispravan OpenSSH zapis javnog kljuca");
+    let text = String::from_utf8_lossy(data);
+    let mut fields = text.split_whitespace();
+    let (key_type, encoded) = fields.next().zip(fields.next()).ok_or_else(malformed)?;
+    let blob = decode_block(encoded).map_err(|_| malformed())?;
+    let (blob_type, rest) = read_length_prefixed(&blob).ok_or_else(malformed)?;
+    if blob_type != key_type.as_bytes() {
+        return Err(malformed());
+    }
+    match key_type {
+        "ssh-rsa" => {
+            let (e, rest) = read_length_prefixed(rest).ok_or_else(malformed)?;
+            let (n, _) = read_length_prefixed(rest).ok_or_else(malformed)?;
+            Ok(PKey::from_rsa(Rsa::from_public_components(
+                BigNum::from_slice(n)?,
+                BigNum::from_slice(e)?,
+            )?)?)
+        }
+        "ssh-ed25519" => {
+            let (key, _) = read_length_prefixed(rest).ok_or_else(malformed)?;
+            Ok(PKey::public_key_from_raw_bytes(key, Id::ED25519)?)
+        }
+        _ => {
+            let (curve, rest) = read_length_prefixed(rest).ok_or_else(malformed)?;
+            let curve = String::from_utf8_lossy(curve);
+            if key_type.strip_prefix("ecdsa-sha2-") != Some(&curve) {
+                return Err(malformed());
+            }
+            let (point, _) = read_length_prefixed(rest).ok_or_else(malformed)?;
+            let group = ec_group_by_name(&curve)?;
+            let mut context = BigNumContext::new()?;
+            let point = EcPoint::from_bytes(&group, point, &mut context)?;
+            Ok(PKey::from_ec_key(EcKey::from_public_key(&group, &point)?)?)
+        }
+    }
+}
+
+// Pozitivan broj ne smije imati postavljen najvisi bit
+fn mpint(value: &BigNumRef) -> Vec<u8> {
+    let mut bytes = value.to_vec();
+    if bytes.first().is_some_and(|byte| byte & 0x80 != 0) {
+        bytes.insert(0, 0);
+    }
+    bytes
+}
+
+fn base64url(data: &[u8]) -> String {
+    encode_block(data)
+        .trim_end_matches('=')
+        .replace('+', "-")
+        .replace('/', "_")
+}
+
+fn from_base64url(text: &str) -> Result<Vec<u8>> {
+    let mut text = text.replace('-', "+").replace('_', "/");
+    while !text.len().is_multiple_of(4) {
+        text.push('=');
+    }
+    decode_block(&text).map_err(|_| anyhow!("Neispravan Base64url zapis u JWK kljucu"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{private_key::is_encrypted, test_util::TempDir};
+    use std::{fs, process::Command};
+
+    fn keys() -> [PKey<Private>; 4] {
+        let ec_key = |curve| {
+            let group = EcGroup::from_curve_name(curve).unwrap();
+            PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+        };
+        [
+            PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap(),
+            PKey::generate_ed25519().unwrap(),
+            ec_key(Nid::X9_62_PRIME256V1),
+            ec_key(Nid::SECP384R1),
+        ]
+    }
+
+    fn is_pkcs1(format: KeyFormat) -> bool {
+        matches!(format, KeyFormat::PemPkcs1 | KeyFormat::DerPkcs1)
+    }
+
+    #[test]
+    fn public_keys_survive_export_and_import_in_every_format() {
+        for pkey in keys() {
+            for format in KeyFormat::ALL {
+                let exported = export_public_key(&pkey, format);
+                if is_pkcs1(format) && pkey.id() != Id::RSA {
+                    assert!(exported.is_err());
+                    continue;
+                }
+                let exported = exported.unwrap();
+                assert_eq!(KeyFormat::detect(&exported), format);
+                assert!(import_public_key(&exported).unwrap().public_eq(&pkey));
+            }
+        }
+    }
+
+    #[test]
+    fn private_keys_survive_export_and_import_in_every_format() {
+        for pkey in keys() {
+            for format in KeyFormat::ALL {
+                let exported = export_private_key(&pkey, format, None);
+                if format == KeyFormat::OpenSsh || (is_pkcs1(format) && pkey.id() != Id::RSA) {
+                    assert!(exported.is_err());
+                    continue;
+                }
+                let exported = exported.unwrap();
+                assert_eq!(KeyFormat::detect(&exported), format);
+                assert!(import_private_key(&exported, None)
+                    .unwrap()
+                    .public_eq(&pkey));
+                // Iz privatnog kljuca se moze uvesti i javni kljuc
+                assert!(import_public_key(&exported).unwrap().public_eq(&pkey));
+            }
+        }
+    }
+
+    #[test]
+    fn exported_private_keys_can_be_protected() {
+        for pkey in keys() {
+            for format in KeyFormat::ALL {
+                let exported = export_private_key(&pkey, format, Some("lozinka"));
+                if !format.supports_encryption() {
+                    assert!(exported.is_err());
+                    continue;
+                }
+                let exported = exported.unwrap();
+                assert!(is_encrypted(&exported));
+                assert_eq!(KeyFormat::detect(&exported), format);
+                assert!(import_private_key(&exported, None).is_err());
+                assert!(import_private_key(&exported, Some("pogresna")).is_err());
+                assert!(import_private_key(&exported, Some("lozinka"))
+                    .unwrap()
+                    .public_eq(&pkey));
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported_keys_are_rejected() {
+        assert!(import_public_key(b"{\"kty\":\"oct\",\"k\":\"AAAA\"}").is_err());
+        assert!(import_public_key(b"ssh-rsa AAAA os2").is_err());
+    }
+
+    #[test]
+    #[ignore = "potreban je program ssh-keygen"]
+    fn openssh_keys_are_readable_by_ssh_keygen() {
+        let dir = TempDir::new("format");
+        let ssh_path = dir.path("kljuc.pub");
+        for pkey in keys() {
+            // ssh-keygen cita zapis i racuna isti otisak, a RSA i EC kljuceve
+            // pretvara u isti SubjectPublicKeyInfo
+            let ssh_key = export_public_key(&pkey, KeyFormat::OpenSsh).unwrap();
+            fs::write(&ssh_path, &ssh_key).unwrap();
+            let ssh_keygen = |args: &[&str]| {
+                let output = Command::new("ssh-keygen")
+                    .args(args)
+                    .arg(&ssh_path)
+                    .output()
+                    .expect("ssh-keygen naredba nije dostupna");
+                assert!(output.status.success());
+                output.stdout
+            };
+            let blob = String::from_utf8(ssh_key).unwrap();
+            let blob = decode_block(blob.split_whitespace().nth(1).unwrap()).unwrap();
+            let fingerprint = encode_block(&openssl::sha::sha256(&blob));
+            let listed = String::from_utf8(ssh_keygen(&["-l", "-f"])).unwrap();
+            assert!(listed.contains(&format!("SHA256:{}", fingerprint.trim_end_matches('='))));
+            if pkey.id() != Id::ED25519 {
+                let converted = ssh_keygen(&["-e", "-m", "PKCS8", "-f"]);
+                assert!(import_public_key(&converted).unwrap().public_eq(&pkey));
+            }
+        }
+    }
+}
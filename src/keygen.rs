@@ -6,11 +6,15 @@ pub struct Keygen {
 }
 
 impl Keygen {
-    pub fn generate_128bit_key(&mut self) -> [u8; 16] {
+    pub fn generate_96bit_nonce(&mut self) -> [u8; 12] {
         self.rng.gen()
     }
 
-    pub fn generate_192bit_key(&mut self) -> [u8; 24] {
+    pub fn generate_56bit_nonce_prefix(&mut self) -> [u8; 7] {
+        self.rng.gen()
+    }
+
+    pub fn generate_128bit_key(&mut self) -> [u8; 16] {
         self.rng.gen()
     }
 
@@ -1,4 +1,6 @@
+use gui::certificate_authority::{CertificateAuthorityMessage, CertificateAuthorityView};
 use gui::encrypt_decrypt::{EncryptDecryptMessage, EncryptDecryptView};
+use gui::envelope::{EnvelopeMessage, EnvelopeView};
 use gui::hash::{HashMessage, HashView};
 use gui::keygen::{GenerateKeysView, KeyGenMessage};
 use gui::navigation::{NavigationButtons, NavigationStateMessage};
@@ -7,21 +9,41 @@ use gui::styled_components::styled_column;
 use iced::widget::{container, scrollable};
 use iced::{executor, Application, Command, Padding, Settings, Theme};
 
+mod certificate;
+mod certificate_authority;
+mod chunked_encryption;
+mod crl;
 mod encryption;
+mod file_container;
 mod file_manip;
+mod fingerprint;
 mod gui;
+mod hashing;
+mod kdf;
+mod key_format;
 mod keygen;
+mod private_key;
+mod signing;
+#[cfg(test)]
+mod test_util;
+mod trust_store;
 
 const PRIVATE_KEY_FILENAME: &str = "privatni_kljuc.txt";
 const PUBLIC_KEY_FILENAME: &str = "javni_kljuc.txt";
 const SECRET_KEY_FILENAME: &str = "tajni_kljuc.txt";
+const CERTIFICATE_FILENAME: &str = "certifikat.pem";
+const CERTIFICATE_REQUEST_FILENAME: &str = "zahtjev_za_certifikat.pem";
+// Certifikat koji je izdalo certifikacijsko tijelo, zajedno s lancem certifikata tijela
+const ISSUED_CERTIFICATE_FILENAME: &str = "izdani_certifikat.pem";
 
 struct Os2Projekt {
     navigation_buttons: NavigationButtons,
     keygen_view: GenerateKeysView,
     encrypt_decrypt_view: EncryptDecryptView,
+    envelope_view: EnvelopeView,
     hashing_view: HashView,
     sign_view: SignView,
+    certificate_authority_view: CertificateAuthorityView,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -29,8 +51,10 @@ pub enum Message {
     NavigationMessage(NavigationStateMessage),
     KeyGenMessage(KeyGenMessage),
     EncryptDecryptMessage(EncryptDecryptMessage),
+    EnvelopeMessage(EnvelopeMessage),
     HashMessage(HashMessage),
     SignMessage(SignMessage),
+    CertificateAuthorityMessage(CertificateAuthorityMessage),
     ErrorMessage,
 }
 
@@ -46,8 +70,10 @@ impl Application for Os2Projekt {
                 navigation_buttons: NavigationButtons::new(),
                 keygen_view: GenerateKeysView::new(),
                 encrypt_decrypt_view: EncryptDecryptView::new(),
+                envelope_view: EnvelopeView::new(),
                 hashing_view: HashView::new(),
                 sign_view: SignView::new(),
+                certificate_authority_view: CertificateAuthorityView::new(),
             },
             Command::none(),
         )
@@ -62,19 +88,32 @@ impl Application for Os2Projekt {
             Message::NavigationMessage(msg) => {
                 self.navigation_buttons.update(msg);
                 self.encrypt_decrypt_view.reset();
+                self.envelope_view.reset();
                 self.hashing_view.reset();
                 self.sign_view.reset();
+                self.certificate_authority_view.reset();
+            }
+            Message::KeyGenMessage(msg) => {
+                if let KeyGenMessage::SelectMinRsaKeyLength(bits) = msg {
+                    self.encrypt_decrypt_view.set_min_rsa_key_length(bits);
+                    self.envelope_view.set_min_rsa_key_length(bits);
+                    self.sign_view.set_min_rsa_key_length(bits);
+                }
+                self.keygen_view.update(msg)
             }
-            Message::KeyGenMessage(msg) => self.keygen_view.update(msg),
             Message::EncryptDecryptMessage(msg) => self.encrypt_decrypt_view.update(msg),
+            Message::EnvelopeMessage(msg) => self.envelope_view.update(msg),
             Message::HashMessage(msg) => self.hashing_view.update(msg),
             Message::SignMessage(msg) => self.sign_view.update(msg),
+            Message::CertificateAuthorityMessage(msg) => {
+                self.certificate_authority_view.update(msg)
+            }
             Message::ErrorMessage => (),
         }
         Command::none()
     }
 
-    fn view(&self) -> iced::Element<Self::Message> {
+    fn view(&self) -> iced::Element<'_, Self::Message> {
         let mut col = styled_column(None).push(
             self.navigation_buttons
                 .view()
@@ -89,12 +128,20 @@ impl Application for Os2Projekt {
                     .view()
                     .map(Message::EncryptDecryptMessage),
             ),
+            NavigationStateMessage::Envelope => {
+                col.push(self.envelope_view.view().map(Message::EnvelopeMessage))
+            }
             NavigationStateMessage::Hashing => {
                 col.push(self.hashing_view.view().map(Message::HashMessage))
             }
             NavigationStateMessage::Sign => {
                 col.push(self.sign_view.view().map(Message::SignMessage))
             }
+            NavigationStateMessage::CertificateAuthority => col.push(
+                self.certificate_authority_view
+                    .view()
+                    .map(Message::CertificateAuthorityMessage),
+            ),
         };
         let main_container = container(scrollable(
             container(col).width(iced::Length::Fill).center_x(),
@@ -0,0 +1,1660 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use openssl::{
+    base64::{decode_block, encode_block},
+    ec::{EcGroup, EcKey},
+    ecdsa::EcdsaSig,
+    nid::Nid,
+    pkey::{HasPublic, Id, PKey, Private, Public},
+    rsa::Padding,
+    sign::{RsaPssSaltlen, Signer, Verifier},
+    x509::X509,
+};
+
+use crate::{
+    certificate::name_to_string,
+    encryption::{check_rsa_key_length, generate_rsa_key, EncryptRsa, RsaKeyParams, ShaHash},
+    file_container::{read_length_prefixed, write_length_prefixed},
+    file_manip::{
+        normalize_line_endings, read_chunks, read_file_to_buffer, trim_trailing_whitespace,
+        write_file, write_file_streamed,
+    },
+    fingerprint::{format_fingerprint, key_fingerprint, short_key_id},
+    hashing::HashAlgorithm,
+    key_format::import_public_key,
+    private_key::{load_private_key, save_private_key},
+    trust_store::{CertificateRevoked, TrustStore},
+    PUBLIC_KEY_FILENAME,
+};
+
+const SIGNATURE_MAGIC: &[u8; 4] = b"OS2P";
+const SIGNATURE_VERSION: u8 = 1;
+const NO_DIGEST_ID: u8 = 0;
+const ATTACHED_MAGIC: &[u8; 4] = b"OS2A";
+const ATTACHED_VERSION: u8 = 1;
+// Potpis unutar poruke je kratak, veca duljina znaci neispravnu datoteku
+const MAX_SIGNATURE_LENGTH: usize = 64 * 1024;
+const ARMOR_BEGIN: &str = "-----BEGIN OS2 POTPISANA PORUKA-----";
+const ARMOR_END: &str = "-----END OS2 POTPISANA PORUKA-----";
+const ARMOR_LINE_LENGTH: usize = 64;
+const ED25519_SIGNATURE_LENGTH: usize = 64;
+// Zadnji bajt kodiranog RSA-PSS potpisa (RFC 8017, 9.1.1)
+const PSS_TRAILER: u8 = 0xbc;
+// RSA_PSS_SALTLEN_AUTO, kod provjere se duljina soli cita iz potpisa
+const PSS_SALTLEN_AUTO: i32 = -2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAlgorithm {
+    Rsa,
+    Ed25519,
+    EcdsaP256,
+    EcdsaP384,
+}
+
+impl KeyAlgorithm {
+    pub const ALL: [KeyAlgorithm; 4] = [
+        KeyAlgorithm::Rsa,
+        KeyAlgorithm::Ed25519,
+        KeyAlgorithm::EcdsaP256,
+        KeyAlgorithm::EcdsaP384,
+    ];
+
+    pub fn of<T: HasPublic>(pkey: &PKey<T>) -> Result<Self> {
+        match pkey.id() {
+            Id::RSA => Ok(KeyAlgorithm::Rsa),
+            Id::ED25519 => Ok(KeyAlgorithm::Ed25519),
+            Id::EC => match pkey.ec_key()?.group().curve_name() {
+                Some(Nid::X9_62_PRIME256V1) => Ok(KeyAlgorithm::EcdsaP256),
+                Some(Nid::SECP384R1) => Ok(KeyAlgorithm::EcdsaP384),
+                _ => Err(anyhow!("Nepodrzana elipticna krivulja")),
+            },
+            _ => Err(anyhow!("Nepodrzana vrsta kljuca")),
+        }
+    }
+}
+
+impl fmt::Display for KeyAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyAlgorithm::Rsa => write!(f, "RSA"),
+            KeyAlgorithm::Ed25519 => write!(f, "Ed25519"),
+            KeyAlgorithm::EcdsaP256 => write!(f, "ECDSA P-256"),
+            KeyAlgorithm::EcdsaP384 => write!(f, "ECDSA P-384"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignaturePadding {
+    Pkcs1,
+    Pss,
+}
+
+impl SignaturePadding {
+    pub const ALL: [SignaturePadding; 2] = [SignaturePadding::Pkcs1, SignaturePadding::Pss];
+}
+
+impl fmt::Display for SignaturePadding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignaturePadding::Pkcs1 => write!(f, "RSA PKCS#1 v1.5"),
+            SignaturePadding::Pss => write!(f, "RSA-PSS"),
+        }
+    }
+}
+
+// Tekstualni nacin potpisuje tekst s normaliziranim zavrsecima redaka (LF, bez zavrsnog),
+// pa potpis vrijedi neovisno o tome koristi li datoteka CR LF ili LF
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureMode {
+    Binary,
+    Text,
+    TextTrimmed,
+}
+
+impl SignatureMode {
+    pub const ALL: [SignatureMode; 3] = [
+        SignatureMode::Binary,
+        SignatureMode::Text,
+        SignatureMode::TextTrimmed,
+    ];
+
+    pub fn canonicalize(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            SignatureMode::Binary => data.to_vec(),
+            SignatureMode::Text => normalize_line_endings(data),
+            SignatureMode::TextTrimmed => trim_trailing_whitespace(&normalize_line_endings(data)),
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            SignatureMode::Binary => 0,
+            SignatureMode::Text => 1,
+            SignatureMode::TextTrimmed => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self> {
+        match id {
+            0 => Ok(SignatureMode::Binary),
+            1 => Ok(SignatureMode::Text),
+            2 => Ok(SignatureMode::TextTrimmed),
+            _ => Err(anyhow!("Nepoznat nacin potpisivanja ({})", id)),
+        }
+    }
+}
+
+impl fmt::Display for SignatureMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureMode::Binary => write!(f, "Binarno"),
+            SignatureMode::Text => write!(f, "Tekst"),
+            SignatureMode::TextTrimmed => write!(f, "Tekst bez zavrsnih razmaka"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureAlgorithm {
+    RsaPkcs1,
+    RsaPss,
+    Ed25519,
+    EcdsaP256,
+    EcdsaP384,
+}
+
+impl SignatureAlgorithm {
+    pub fn for_key(key: KeyAlgorithm, padding: SignaturePadding) -> Self {
+        match (key, padding) {
+            (KeyAlgorithm::Rsa, SignaturePadding::Pkcs1) => SignatureAlgorithm::RsaPkcs1,
+            (KeyAlgorithm::Rsa, SignaturePadding::Pss) => SignatureAlgorithm::RsaPss,
+            (KeyAlgorithm::Ed25519, _) => SignatureAlgorithm::Ed25519,
+            (KeyAlgorithm::EcdsaP256, _) => SignatureAlgorithm::EcdsaP256,
+            (KeyAlgorithm::EcdsaP384, _) => SignatureAlgorithm::EcdsaP384,
+        }
+    }
+
+    pub fn key_algorithm(&self) -> KeyAlgorithm {
+        match self {
+            SignatureAlgorithm::RsaPkcs1 | SignatureAlgorithm::RsaPss => KeyAlgorithm::Rsa,
+            SignatureAlgorithm::Ed25519 => KeyAlgorithm::Ed25519,
+            SignatureAlgorithm::EcdsaP256 => KeyAlgorithm::EcdsaP256,
+            SignatureAlgorithm::EcdsaP384 => KeyAlgorithm::EcdsaP384,
+        }
+    }
+
+    // Ed25519 interno koristi SHA-512 i ne prima vanjski sazetak
+    pub fn default_digest(&self) -> Option<HashAlgorithm> {
+        match self {
+            SignatureAlgorithm::RsaPkcs1
+            | SignatureAlgorithm::RsaPss
+            | SignatureAlgorithm::EcdsaP256 => Some(HashAlgorithm::Sha256),
+            SignatureAlgorithm::EcdsaP384 => Some(HashAlgorithm::Sha384),
+            SignatureAlgorithm::Ed25519 => None,
+        }
+    }
+
+    // Odabrani sazetak ili zadani sazetak algoritma, Ed25519 ga zanemaruje
+    pub fn digest(&self, digest: Option<HashAlgorithm>) -> Option<HashAlgorithm> {
+        match self {
+            SignatureAlgorithm::Ed25519 => None,
+            _ => digest.or_else(|| self.default_digest()),
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            SignatureAlgorithm::RsaPkcs1 => 1,
+            SignatureAlgorithm::RsaPss => 2,
+            SignatureAlgorithm::Ed25519 => 3,
+            SignatureAlgorithm::EcdsaP256 => 4,
+            SignatureAlgorithm::EcdsaP384 => 5,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self> {
+        match id {
+            1 => Ok(SignatureAlgorithm::RsaPkcs1),
+            2 => Ok(SignatureAlgorithm::RsaPss),
+            3 => Ok(SignatureAlgorithm::Ed25519),
+            4 => Ok(SignatureAlgorithm::EcdsaP256),
+            5 => Ok(SignatureAlgorithm::EcdsaP384),
+            _ => Err(anyhow!("Nepoznat algoritam potpisa ({})", id)),
+        }
+    }
+}
+
+impl fmt::Display for SignatureAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureAlgorithm::RsaPkcs1 => write!(f, "RSA PKCS#1 v1.5"),
+            SignatureAlgorithm::RsaPss => write!(f, "RSA-PSS"),
+            SignatureAlgorithm::Ed25519 => write!(f, "Ed25519"),
+            SignatureAlgorithm::EcdsaP256 => write!(f, "ECDSA P-256"),
+            SignatureAlgorithm::EcdsaP384 => write!(f, "ECDSA P-384"),
+        }
+    }
+}
+
+pub struct SignatureMetadata {
+    pub signer_fingerprint: Vec<u8>,
+    // Sekunde od 1.1.1970. (UTC)
+    pub created: u64,
+    pub filename: String,
+    pub file_size: u64,
+}
+
+pub struct SignatureInfo {
+    pub algorithm: SignatureAlgorithm,
+    pub digest: Option<HashAlgorithm>,
+    pub mode: SignatureMode,
+    // Potpisani su zajedno s podacima, nema ih samo kod starih RSA potpisa bez zaglavlja
+    pub metadata: Option<SignatureMetadata>,
+    // Prvi je certifikat potpisnika, prazan ako potpisu nije prilozen lanac
+    pub certificates: Vec<X509>,
+}
+
+impl SignatureInfo {
+    // [magic][verzija][algoritam potpisa][algoritam sazetka][nacin potpisivanja]
+    // [duljina][otisak kljuca potpisnika][vrijeme (u64 BE)][velicina (u64 BE)][ime datoteke]
+    // [duljina][certifikati u DER zapisu, svaki s prefiksom duljine]
+    fn header(&self) -> Result<Vec<u8>> {
+        let mut out = SIGNATURE_MAGIC.to_vec();
+        out.push(SIGNATURE_VERSION);
+        out.push(self.algorithm.id());
+        out.push(match self.digest {
+            Some(digest) => digest.id(),
+            None => NO_DIGEST_ID,
+        });
+        out.push(self.mode.id());
+        let mut metadata = Vec::new();
+        if let Some(m) = &self.metadata {
+            write_length_prefixed(&mut metadata, &m.signer_fingerprint);
+            metadata.extend_from_slice(&m.created.to_be_bytes());
+            metadata.extend_from_slice(&m.file_size.to_be_bytes());
+            write_length_prefixed(&mut metadata, m.filename.as_bytes());
+        }
+        write_length_prefixed(&mut out, &metadata);
+        let mut certificates = Vec::new();
+        for certificate in &self.certificates {
+            write_length_prefixed(&mut certificates, &certificate.to_der()?);
+        }
+        write_length_prefixed(&mut out, &certificates);
+        Ok(out)
+    }
+
+    // Vraca podatke o potpisu, potpisano zaglavlje i sam potpis
+    fn parse(sig: &[u8]) -> Result<(Self, &[u8], &[u8])> {
+        let malformed = || anyhow!("Neispravan format potpisa");
+        let fixed_length = SIGNATURE_MAGIC.len() + 4;
+        if !sig.starts_with(SIGNATURE_MAGIC) || sig.len() < fixed_length {
+            return Err(malformed());
+        }
+        let version = sig[SIGNATURE_MAGIC.len()];
+        if version != SIGNATURE_VERSION {
+            return Err(anyhow!("Nepodrzana verzija potpisa ({})", version));
+        }
+        let algorithm = SignatureAlgorithm::from_id(sig[SIGNATURE_MAGIC.len() + 1])?;
+        let digest = match sig[SIGNATURE_MAGIC.len() + 2] {
+            NO_DIGEST_ID => None,
+            id => Some(HashAlgorithm::from_id(id)?),
+        };
+        let mode = SignatureMode::from_id(sig[SIGNATURE_MAGIC.len() + 3])?;
+
+        let (metadata, rest) = read_length_prefixed(&sig[fixed_length..]).ok_or_else(malformed)?;
+        let (signer_fingerprint, numbers) = read_length_prefixed(metadata).ok_or_else(malformed)?;
+        if numbers.len() < 16 {
+            return Err(malformed());
+        }
+        let (numbers, filename) = numbers.split_at(16);
+        let (filename, _) = read_length_prefixed(filename).ok_or_else(malformed)?;
+        let mut info = SignatureInfo {
+            algorithm,
+            digest,
+            mode,
+            metadata: Some(SignatureMetadata {
+                signer_fingerprint: signer_fingerprint.to_vec(),
+                created: u64::from_be_bytes(numbers[..8].try_into()?),
+                file_size: u64::from_be_bytes(numbers[8..].try_into()?),
+                filename: String::from_utf8_lossy(filename).into_owned(),
+            }),
+            certificates: Vec::new(),
+        };
+
+        let (mut certificates, signature) = read_length_prefixed(rest).ok_or_else(malformed)?;
+        while !certificates.is_empty() {
+            let (der, rest) = read_length_prefixed(certificates).ok_or_else(malformed)?;
+            info.certificates
+                .push(X509::from_der(der).map_err(|_| anyhow!("Neispravan certifikat u potpisu"))?);
+            certificates = rest;
+        }
+        let header_length = sig.len() - signature.len();
+        Ok((info, &sig[..header_length], signature))
+    }
+
+    // Starija verzija programa potpisivala je SHA-256 sazetak sirovom RSA operacijom
+    // privatnog kljuca, bez zaglavlja i bez DigestInfo zapisa
+    fn raw_rsa() -> Self {
+        SignatureInfo {
+            algorithm: SignatureAlgorithm::RsaPkcs1,
+            digest: Some(HashAlgorithm::Sha256),
+            mode: SignatureMode::Binary,
+            metadata: None,
+            certificates: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for SignatureInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.metadata.is_none() {
+            return write!(f, "Stari RSA potpis bez zaglavlja (SHA-256)");
+        }
+        write!(f, "Algoritam potpisa: {}", self.algorithm)?;
+        if let Some(digest) = self.digest {
+            write!(f, ", sazetak: {}", digest)?;
+        }
+        write!(f, ", nacin: {}", self.mode)?;
+        if let Some(m) = &self.metadata {
+            write!(
+                f,
+                "\nOtisak kljuca potpisnika: {}\nVrijeme potpisa: {}\nDatoteka: {} ({} B)",
+                format_fingerprint(&m.signer_fingerprint),
+                format_timestamp(m.created),
+                m.filename,
+                m.file_size
+            )?;
+        }
+        if let Some(certificate) = self.certificates.first() {
+            write!(
+                f,
+                "\nCertifikat potpisnika: {} (izdavatelj: {})",
+                name_to_string(certificate.subject_name()),
+                name_to_string(certificate.issuer_name())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum VerificationOutcome {
+    Valid,
+    // Potpis je ispravnog oblika, ali sazetak ne odgovara datoteci
+    FileChanged,
+    SignatureDamaged(String),
+    WrongKey(String),
+    // Lanac certifikata potpisnika ne vodi do pouzdanog korijenskog certifikata
+    UntrustedCertificate(String),
+    // Certifikat iz lanca potpisnika je opozvan, opis sadrzi vrijeme i razlog opoziva
+    RevokedCertificate(String),
+    Io(String),
+}
+
+impl fmt::Display for VerificationOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationOutcome::Valid => write!(f, "Potpis valjan."),
+            VerificationOutcome::FileChanged => write!(
+                f,
+                "Potpis nije valjan: datoteka je izmijenjena nakon potpisivanja. \
+                 Potpis je ispravnog oblika, ali sazetak datoteke ne odgovara potpisanom sazetku."
+            ),
+            VerificationOutcome::SignatureDamaged(reason) => write!(
+                f,
+                "Potpis nije valjan: datoteka s potpisom je ostecena ({}).",
+                reason
+            ),
+            VerificationOutcome::WrongKey(reason) => {
+                write!(f, "Potpis nije kreiran odabranim kljucem: {}.", reason)
+            }
+            VerificationOutcome::UntrustedCertificate(reason) => {
+                write!(f, "Potpisnik nije pouzdan: {}.", reason)
+            }
+            VerificationOutcome::RevokedCertificate(reason) => {
+                write!(f, "Potpis nije valjan: {}.", reason)
+            }
+            VerificationOutcome::Io(reason) => {
+                write!(
+                    f,
+                    "Provjera nije provedena, greska pri radu s datotekom: {}",
+                    reason
+                )
+            }
+        }
+    }
+}
+
+pub struct Verification {
+    pub outcome: VerificationOutcome,
+    // Nema ga ako se potpis ne moze procitati
+    pub info: Option<SignatureInfo>,
+    // Subjekt certifikata potpisnika, samo za valjane potpise provjerene lancem certifikata
+    pub signer: Option<String>,
+}
+
+impl Verification {
+    pub fn is_valid(&self) -> bool {
+        matches!(self.outcome, VerificationOutcome::Valid)
+    }
+
+    // Greske citanja su I/O greske, a sve ostale znace neispravan zapis potpisa
+    fn from_error(error: anyhow::Error) -> Self {
+        let outcome = match error.downcast_ref::<io::Error>() {
+            Some(_) => VerificationOutcome::Io(error.to_string()),
+            None => VerificationOutcome::SignatureDamaged(error.to_string()),
+        };
+        Self {
+            outcome,
+            info: None,
+            signer: None,
+        }
+    }
+}
+
+// "2022-11-30 14:05:00 UTC"
+pub fn format_timestamp(timestamp: u64) -> String {
+    let (year, month, day, seconds) = utc_date(timestamp);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+// Godina, mjesec, dan i sekunde od pocetka dana
+pub fn utc_date(timestamp: u64) -> (i64, i64, i64, u64) {
+    let days = (timestamp / 86400) as i64;
+    let seconds = timestamp % 86400;
+    // Pretvorba broja dana u datum gregorijanskog kalendara
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day, seconds)
+}
+
+pub fn generate_key_pair(
+    algorithm: KeyAlgorithm,
+    rsa_params: RsaKeyParams,
+) -> Result<PKey<Private>> {
+    Ok(match algorithm {
+        KeyAlgorithm::Rsa => PKey::from_rsa(generate_rsa_key(rsa_params)?)?,
+        KeyAlgorithm::Ed25519 => PKey::generate_ed25519()?,
+        KeyAlgorithm::EcdsaP256 => generate_ec_key(Nid::X9_62_PRIME256V1)?,
+        KeyAlgorithm::EcdsaP384 => generate_ec_key(Nid::SECP384R1)?,
+    })
+}
+
+pub fn generate_save_keys(
+    algorithm: KeyAlgorithm,
+    rsa_params: RsaKeyParams,
+    passphrase: Option<&str>,
+) -> Result<()> {
+    if algorithm == KeyAlgorithm::Rsa {
+        EncryptRsa::new_save_keys(rsa_params, passphrase)?;
+        return Ok(());
+    }
+    let pkey = generate_key_pair(algorithm, rsa_params)?;
+    save_private_key(&pkey, None, passphrase)?;
+    write_file(PUBLIC_KEY_FILENAME, &pkey.public_key_to_pem()?, false)?;
+    Ok(())
+}
+
+// Provjerava se samo RSA, ostali algoritmi imaju fiksnu velicinu kljuca
+pub fn check_key_length<T: HasPublic>(pkey: &PKey<T>, min_rsa_key_length: u32) -> Result<()> {
+    if pkey.id() == Id::RSA {
+        check_rsa_key_length(pkey.bits(), min_rsa_key_length)?;
+    }
+    Ok(())
+}
+
+fn generate_ec_key(curve: Nid) -> Result<PKey<Private>> {
+    let group = EcGroup::from_curve_name(curve)?;
+    Ok(PKey::from_ec_key(EcKey::generate(&group)?)?)
+}
+
+pub struct SigningKey {
+    pkey: PKey<Private>,
+    padding: SignaturePadding,
+    digest: Option<HashAlgorithm>,
+    mode: SignatureMode,
+    certificates: Vec<X509>,
+}
+
+impl SigningKey {
+    pub fn new(pkey: PKey<Private>) -> Self {
+        Self {
+            pkey,
+            padding: SignaturePadding::Pkcs1,
+            digest: None,
+            mode: SignatureMode::Binary,
+            certificates: Vec::new(),
+        }
+    }
+
+    pub fn from_file(
+        filename: Option<&str>,
+        passphrase: Option<&str>,
+        min_rsa_key_length: u32,
+    ) -> Result<Self> {
+        let pkey = load_private_key(filename, passphrase)?;
+        check_key_length(&pkey, min_rsa_key_length)?;
+        Ok(Self::new(pkey))
+    }
+
+    pub fn set_signature_padding(&mut self, padding: SignaturePadding) {
+        self.padding = padding;
+    }
+
+    pub fn set_digest(&mut self, digest: HashAlgorithm) {
+        self.digest = Some(digest);
+    }
+
+    pub fn set_mode(&mut self, mode: SignatureMode) {
+        self.mode = mode;
+    }
+
+    // Lanac se prilaze potpisu, a prvi certifikat mora biti certifikat kljuca za potpisivanje
+    pub fn set_certificate_chain(&mut self, certificates: Vec<X509>) -> Result<()> {
+        if let Some(certificate) = certificates.first() {
+            if !certificate.public_key()?.public_eq(&self.pkey) {
+                return Err(anyhow!(
+                    "Certifikat potpisnika ne odgovara kljucu za potpisivanje"
+                ));
+            }
+        }
+        self.certificates = certificates;
+        Ok(())
+    }
+
+    pub fn algorithm(&self) -> Result<SignatureAlgorithm> {
+        Ok(SignatureAlgorithm::for_key(
+            KeyAlgorithm::of(&self.pkey)?,
+            self.padding,
+        ))
+    }
+
+    // Zaglavlje s podacima o potpisniku i datoteci se potpisuje prije samih podataka
+    pub fn sign_file(&self, filename: &str) -> Result<Vec<u8>> {
+        let algorithm = self.algorithm()?;
+        let digest = algorithm.digest(self.digest);
+        let mut file = File::open(filename)?;
+        let info = SignatureInfo {
+            algorithm,
+            digest,
+            mode: self.mode,
+            metadata: Some(SignatureMetadata {
+                signer_fingerprint: key_fingerprint(&self.pkey)?,
+                created: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+                filename: Path::new(filename)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                file_size: file.metadata()?.len(),
+            }),
+            certificates: self.certificates.clone(),
+        };
+        let mut out = info.header()?;
+
+        let signature = match (self.mode, digest) {
+            (SignatureMode::Binary, Some(_)) => sign_reader(
+                &self.pkey,
+                algorithm,
+                digest,
+                &mut (&out[..]).chain(&mut file),
+            )?,
+            // Ed25519 potpisuje cijelu poruku odjednom, a tekst se normalizira u memoriji
+            (mode, _) => {
+                let mut data = out.clone();
+                data.extend_from_slice(&mode.canonicalize(&read_file_to_buffer(filename)?));
+                sign_with_digest(&self.pkey, algorithm, digest, &data)?
+            }
+        };
+        out.extend_from_slice(&signature);
+        Ok(out)
+    }
+
+    // Samo potpis sadrzaja datoteke, bez zaglavlja, kao `openssl dgst -sign` ili
+    // `openssl pkeyutl -sign -rawin` za Ed25519; nacin potpisivanja teksta se ne primjenjuje
+    pub fn sign_file_detached(&self, filename: &str) -> Result<Vec<u8>> {
+        let algorithm = self.algorithm()?;
+        match algorithm.digest(self.digest) {
+            Some(digest) => sign_reader(
+                &self.pkey,
+                algorithm,
+                Some(digest),
+                &mut File::open(filename)?,
+            ),
+            None => sign_with_digest(&self.pkey, algorithm, None, &read_file_to_buffer(filename)?),
+        }
+    }
+
+    // Poruka s potpisom: [magic][verzija][potpis kao kod sign_file s prefiksom duljine][poruka]
+    pub fn sign_attached_file(
+        &self,
+        filename: &str,
+        output_filename: &str,
+        armored: bool,
+    ) -> Result<()> {
+        let mut header = ATTACHED_MAGIC.to_vec();
+        header.push(ATTACHED_VERSION);
+        write_length_prefixed(&mut header, &self.sign_file(filename)?);
+
+        if armored {
+            header.extend_from_slice(&read_file_to_buffer(filename)?);
+            return write_file(output_filename, armor(&header).as_bytes(), false);
+        }
+        write_file_streamed(output_filename, |writer| {
+            writer.write_all(&header)?;
+            io::copy(&mut File::open(filename)?, writer)?;
+            Ok(())
+        })
+    }
+}
+
+// Provjera potpisa samo javnim kljucem potpisnika ili lancem certifikata prilozenim potpisu
+pub struct VerifyKey {
+    pkey: Option<PKey<Public>>,
+    trust_store: Option<TrustStore>,
+}
+
+impl VerifyKey {
+    pub fn from_file(filename: Option<&str>, min_rsa_key_length: u32) -> Result<Self> {
+        let public_key = match read_file_to_buffer(filename.unwrap_or(PUBLIC_KEY_FILENAME)) {
+            Ok(key) => key,
+            Err(error) => {
+                return Err(anyhow!("Ne postoji javni kljuc | {:?}", error));
+            }
+        };
+        let pkey = import_public_key(&public_key)?;
+        check_key_length(&pkey, min_rsa_key_length)?;
+        Ok(Self {
+            pkey: Some(pkey),
+            trust_store: None,
+        })
+    }
+
+    // Kljuc potpisnika se uzima iz certifikata prilozenog potpisu
+    pub fn from_trust_store(trust_store: TrustStore) -> Self {
+        Self {
+            pkey: None,
+            trust_store: Some(trust_store),
+        }
+    }
+
+    pub fn set_trust_store(&mut self, trust_store: TrustStore) {
+        self.trust_store = Some(trust_store);
+    }
+
+    fn public_key(&self) -> Result<&PKey<Public>> {
+        self.pkey
+            .as_ref()
+            .ok_or_else(|| anyhow!("Javni kljuc potpisnika nije odabran"))
+    }
+
+    // Potpisi bez zaglavlja provjeravaju se kao stari RSA potpisi
+    pub fn verify_file_signature(&self, filename: &str, signature_filename: &str) -> Verification {
+        let sig = match read_file_to_buffer(signature_filename) {
+            Ok(sig) => sig,
+            Err(e) => return Verification::from_error(e),
+        };
+        match File::open(filename) {
+            Ok(mut file) => self.verify_signed_reader(&sig, &mut file),
+            Err(e) => Verification::from_error(e.into()),
+        }
+    }
+
+    // Poruka se zapisuje u izlaznu datoteku samo ako je potpis valjan
+    pub fn verify_attached_file(&self, filename: &str, output_filename: &str) -> Verification {
+        self.verify_attached(filename, output_filename)
+            .unwrap_or_else(Verification::from_error)
+    }
+
+    fn verify_attached(&self, filename: &str, output_filename: &str) -> Result<Verification> {
+        let mut file = File::open(filename)?;
+        let mut magic = Vec::with_capacity(ATTACHED_MAGIC.len());
+        (&mut file)
+            .take(ATTACHED_MAGIC.len() as u64)
+            .read_to_end(&mut magic)?;
+
+        if magic == ATTACHED_MAGIC {
+            let sig = read_attached_signature(&mut file)?;
+            let message_offset = file.stream_position()?;
+            let verification = self.verify_signed_reader(&sig, &mut file);
+            if !verification.is_valid() {
+                return Ok(verification);
+            }
+            file.seek(SeekFrom::Start(message_offset))?;
+            write_file_streamed(output_filename, |writer| {
+                io::copy(&mut file, writer)?;
+                Ok(())
+            })?;
+            return Ok(verification);
+        }
+
+        file.seek(SeekFrom::Start(0))?;
+        let mut armored = Vec::new();
+        file.read_to_end(&mut armored)?;
+        let armored =
+            String::from_utf8(armored).map_err(|_| anyhow!("Datoteka nije poruka s potpisom"))?;
+        let data = dearmor(&armored)?;
+        let mut reader = data
+            .strip_prefix(ATTACHED_MAGIC)
+            .ok_or_else(|| anyhow!("Datoteka nije poruka s potpisom"))?;
+        let sig = read_attached_signature(&mut reader)?;
+        let message = reader;
+        let verification = self.verify_signed_reader(&sig, &mut &message[..]);
+        if verification.is_valid() {
+            write_file(output_filename, message, false)?;
+        }
+        Ok(verification)
+    }
+
+    fn verify_signed_reader<R: Read>(&self, sig: &[u8], reader: &mut R) -> Verification {
+        let (info, signed_header, signature) = match self.parse_signature(sig) {
+            Ok(parsed) => parsed,
+            Err(e) => return Verification::from_error(e),
+        };
+        let (outcome, signer) = match self.signer_key(&info) {
+            Ok((pkey, signer)) => (
+                check_signature(&pkey, &info, signed_header, signature, reader)
+                    .unwrap_or_else(|e| Verification::from_error(e).outcome),
+                signer,
+            ),
+            Err(outcome) => (outcome, None),
+        };
+        let signer = signer.filter(|_| matches!(outcome, VerificationOutcome::Valid));
+        Verification {
+            outcome,
+            info: Some(info),
+            signer,
+        }
+    }
+
+    // Uz pouzdane korijenske certifikate kljuc potpisnika je u provjerenom lancu certifikata,
+    // a potpisi bez lanca se provjeravaju odabranim javnim kljucem ako postoji
+    fn signer_key(
+        &self,
+        info: &SignatureInfo,
+    ) -> Result<(PKey<Public>, Option<String>), VerificationOutcome> {
+        if let Some(trust_store) = &self.trust_store {
+            if !info.certificates.is_empty() || self.pkey.is_none() {
+                let signer = trust_store.verify_chain(&info.certificates).map_err(|e| {
+                    match e.downcast_ref::<CertificateRevoked>() {
+                        Some(_) => VerificationOutcome::RevokedCertificate(e.to_string()),
+                        None => VerificationOutcome::UntrustedCertificate(e.to_string()),
+                    }
+                })?;
+                let pkey = info.certificates[0]
+                    .public_key()
+                    .map_err(|e| VerificationOutcome::SignatureDamaged(e.to_string()))?;
+                return Ok((pkey, Some(signer)));
+            }
+        }
+        self.public_key()
+            .map(|pkey| (pkey.clone(), None))
+            .map_err(|e| VerificationOutcome::WrongKey(e.to_string()))
+    }
+
+    fn parse_signature<'a>(&self, sig: &'a [u8]) -> Result<(SignatureInfo, &'a [u8], &'a [u8])> {
+        if sig.starts_with(SIGNATURE_MAGIC) {
+            return SignatureInfo::parse(sig);
+        }
+        Ok((SignatureInfo::raw_rsa(), &[], sig))
+    }
+}
+
+// Redom se provjeravaju kljuc potpisnika, oblik potpisa i na kraju sazetak datoteke
+fn check_signature<R: Read>(
+    pkey: &PKey<Public>,
+    info: &SignatureInfo,
+    signed_header: &[u8],
+    signature: &[u8],
+    reader: &mut R,
+) -> Result<VerificationOutcome> {
+    let key_algorithm = KeyAlgorithm::of(pkey)?;
+    if info.algorithm.key_algorithm() != key_algorithm {
+        return Ok(VerificationOutcome::WrongKey(format!(
+            "potpis je kreiran algoritmom {}, a odabrani kljuc je {}",
+            info.algorithm, key_algorithm
+        )));
+    }
+    let metadata = match &info.metadata {
+        Some(metadata) => metadata,
+        None => return check_raw_rsa_signature(pkey, signature, reader),
+    };
+    let fingerprint = key_fingerprint(pkey)?;
+    if metadata.signer_fingerprint != fingerprint {
+        return Ok(VerificationOutcome::WrongKey(format!(
+            "ID kljuca potpisnika je {}, a odabranog kljuca {}",
+            short_key_id(&metadata.signer_fingerprint),
+            short_key_id(&fingerprint)
+        )));
+    }
+    if let Err(e) = check_signature_structure(pkey, info.algorithm, signature) {
+        return Ok(VerificationOutcome::SignatureDamaged(e.to_string()));
+    }
+
+    let valid = match (info.mode, info.digest) {
+        (SignatureMode::Binary, Some(_)) => verify_reader(
+            pkey,
+            info.algorithm,
+            info.digest,
+            &mut signed_header.chain(reader),
+            signature,
+        )?,
+        (mode, _) => {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+            let data = [signed_header, &mode.canonicalize(&data)].concat();
+            verify_with_digest(pkey, info.algorithm, info.digest, &data, signature)?
+        }
+    };
+    Ok(match valid {
+        true => VerificationOutcome::Valid,
+        false => VerificationOutcome::FileChanged,
+    })
+}
+
+// Stara verzija prije racunanja sazetka uklanja jedan zavrsni prijelom retka (CR LF, LF ili CR)
+fn check_raw_rsa_signature<R: Read>(
+    pkey: &PKey<Public>,
+    signature: &[u8],
+    reader: &mut R,
+) -> Result<VerificationOutcome> {
+    if let Err(e) = check_signature_structure(pkey, SignatureAlgorithm::RsaPkcs1, signature) {
+        // Bez otiska potpisnika RSA potpis drugog kljuca izgleda kao osteceni potpis
+        return Ok(VerificationOutcome::SignatureDamaged(format!(
+            "{} ili je kreiran drugim kljucem",
+            e
+        )));
+    }
+    let rsa = pkey.rsa()?;
+    let mut decrypted = vec![0; rsa.size() as usize];
+    let length = rsa.public_decrypt(signature, &mut decrypted, Padding::PKCS1)?;
+
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    let data = data
+        .strip_suffix(b"\r\n")
+        .or_else(|| data.strip_suffix(b"\n"))
+        .or_else(|| data.strip_suffix(b"\r"))
+        .unwrap_or(&data);
+    Ok(match ShaHash::hash(data)? == decrypted[..length] {
+        true => VerificationOutcome::Valid,
+        false => VerificationOutcome::FileChanged,
+    })
+}
+
+// Oblik potpisa se moze provjeriti bez podataka: duljina, DER zapis kod ECDSA,
+// a kod RSA se potpis dekriptira javnim kljucem i provjerava se nadopuna
+fn check_signature_structure<T: HasPublic>(
+    pkey: &PKey<T>,
+    algorithm: SignatureAlgorithm,
+    signature: &[u8],
+) -> Result<()> {
+    match algorithm {
+        SignatureAlgorithm::RsaPkcs1 | SignatureAlgorithm::RsaPss => {
+            let rsa = pkey.rsa()?;
+            let length = rsa.size() as usize;
+            if signature.len() != length {
+                return Err(anyhow!(
+                    "potpis ima {} B, a RSA potpis ovim kljucem ima {} B",
+                    signature.len(),
+                    length
+                ));
+            }
+            let mut decrypted = vec![0; length];
+            let encoded = match algorithm {
+                SignatureAlgorithm::RsaPkcs1 => rsa
+                    .public_decrypt(signature, &mut decrypted, Padding::PKCS1)
+                    .is_ok(),
+                _ => rsa
+                    .public_decrypt(signature, &mut decrypted, Padding::NONE)
+                    .is_ok_and(|length| decrypted[..length].last() == Some(&PSS_TRAILER)),
+            };
+            if !encoded {
+                return Err(anyhow!("neispravna nadopuna {} potpisa", algorithm));
+            }
+        }
+        SignatureAlgorithm::Ed25519 => {
+            if signature.len() != ED25519_SIGNATURE_LENGTH {
+                return Err(anyhow!(
+                    "potpis ima {} B, a Ed25519 potpis {} B",
+                    signature.len(),
+                    ED25519_SIGNATURE_LENGTH
+                ));
+            }
+        }
+        SignatureAlgorithm::EcdsaP256 | SignatureAlgorithm::EcdsaP384 => {
+            let canonical = EcdsaSig::from_der(signature).and_then(|sig| sig.to_der());
+            if canonical.ok().as_deref() != Some(signature) {
+                return Err(anyhow!("potpis nije ispravan ECDSA DER zapis"));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_attached_signature<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+    let malformed = || anyhow!("Neispravan format poruke s potpisom");
+    let mut header = [0; 5];
+    reader.read_exact(&mut header).map_err(|_| malformed())?;
+    if header[0] != ATTACHED_VERSION {
+        return Err(anyhow!(
+            "Nepodrzana verzija poruke s potpisom ({})",
+            header[0]
+        ));
+    }
+    let length = u32::from_be_bytes(header[1..].try_into()?) as usize;
+    if length > MAX_SIGNATURE_LENGTH {
+        return Err(malformed());
+    }
+    let mut sig = vec![0; length];
+    reader.read_exact(&mut sig).map_err(|_| malformed())?;
+    Ok(sig)
+}
+
+// ASCII zapis: Base64 u retcima od 64 znaka izmedu oznaka pocetka i kraja
+fn armor(data: &[u8]) -> String {
+    let encoded = encode_block(data);
+    let mut out = format!("{}\n", ARMOR_BEGIN);
+    for line in encoded.as_bytes().chunks(ARMOR_LINE_LENGTH) {
+        out.push_str(std::str::from_utf8(line).unwrap_or_default());
+        out.push('\n');
+    }
+    out.push_str(ARMOR_END);
+    out.push('\n');
+    out
+}
+
+fn dearmor(text: &str) -> Result<Vec<u8>> {
+    let malformed = || anyhow!("Neispravan ASCII zapis poruke s potpisom");
+    let encoded = text
+        .trim()
+        .strip_prefix(ARMOR_BEGIN)
+        .and_then(|text| text.strip_suffix(ARMOR_END))
+        .ok_or_else(malformed)?;
+    let encoded: String = encoded.split_whitespace().collect();
+    decode_block(&encoded).map_err(|_| malformed())
+}
+
+// Sa zadanim sazetkom kompatibilno s `openssl dgst -sha256 -sign`
+pub fn sign_with_algorithm(
+    pkey: &PKey<Private>,
+    algorithm: SignatureAlgorithm,
+    data: &[u8],
+) -> Result<Vec<u8>> {
+    sign_with_digest(pkey, algorithm, algorithm.default_digest(), data)
+}
+
+pub fn sign_with_digest(
+    pkey: &PKey<Private>,
+    algorithm: SignatureAlgorithm,
+    digest: Option<HashAlgorithm>,
+    data: &[u8],
+) -> Result<Vec<u8>> {
+    let mut signer = new_signer(pkey, algorithm, digest)?;
+    Ok(signer.sign_oneshot_to_vec(data)?)
+}
+
+// Podaci se potpisuju u dijelovima, Ed25519 to ne podrzava
+pub fn sign_reader<R: Read>(
+    pkey: &PKey<Private>,
+    algorithm: SignatureAlgorithm,
+    digest: Option<HashAlgorithm>,
+    reader: &mut R,
+) -> Result<Vec<u8>> {
+    if digest.is_none() {
+        return Err(streaming_unsupported(algorithm));
+    }
+    let mut signer = new_signer(pkey, algorithm, digest)?;
+    read_chunks(reader, |chunk| Ok(signer.update(chunk)?))?;
+    Ok(signer.sign_to_vec()?)
+}
+
+fn new_signer(
+    pkey: &PKey<Private>,
+    algorithm: SignatureAlgorithm,
+    digest: Option<HashAlgorithm>,
+) -> Result<Signer<'_>> {
+    check_digest(algorithm, digest)?;
+    let mut signer = match digest {
+        Some(digest) => Signer::new(digest.message_digest()?, pkey)?,
+        None => Signer::new_without_digest(pkey)?,
+    };
+    match (algorithm, digest) {
+        (SignatureAlgorithm::RsaPkcs1, _) => signer.set_rsa_padding(Padding::PKCS1)?,
+        (SignatureAlgorithm::RsaPss, Some(digest)) => {
+            signer.set_rsa_padding(Padding::PKCS1_PSS)?;
+            signer.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+            signer.set_rsa_mgf1_md(digest.message_digest()?)?;
+        }
+        _ => (),
+    }
+    Ok(signer)
+}
+
+pub fn verify_with_algorithm<T: HasPublic>(
+    pkey: &PKey<T>,
+    algorithm: SignatureAlgorithm,
+    data: &[u8],
+    signature: &[u8],
+) -> Result<bool> {
+    verify_with_digest(pkey, algorithm, algorithm.default_digest(), data, signature)
+}
+
+pub fn verify_with_digest<T: HasPublic>(
+    pkey: &PKey<T>,
+    algorithm: SignatureAlgorithm,
+    digest: Option<HashAlgorithm>,
+    data: &[u8],
+    signature: &[u8],
+) -> Result<bool> {
+    let mut verifier = new_verifier(pkey, algorithm, digest)?;
+    Ok(verifier.verify_oneshot(signature, data)?)
+}
+
+pub fn verify_reader<T: HasPublic, R: Read>(
+    pkey: &PKey<T>,
+    algorithm: SignatureAlgorithm,
+    digest: Option<HashAlgorithm>,
+    reader: &mut R,
+    signature: &[u8],
+) -> Result<bool> {
+    if digest.is_none() {
+        return Err(streaming_unsupported(algorithm));
+    }
+    let mut verifier = new_verifier(pkey, algorithm, digest)?;
+    read_chunks(reader, |chunk| Ok(verifier.update(chunk)?))?;
+    Ok(verifier.verify(signature)?)
+}
+
+fn new_verifier<T: HasPublic>(
+    pkey: &PKey<T>,
+    algorithm: SignatureAlgorithm,
+    digest: Option<HashAlgorithm>,
+) -> Result<Verifier<'_>> {
+    check_digest(algorithm, digest)?;
+    let mut verifier = match digest {
+        Some(digest) => Verifier::new(digest.message_digest()?, pkey)?,
+        None => Verifier::new_without_digest(pkey)?,
+    };
+    match (algorithm, digest) {
+        (SignatureAlgorithm::RsaPkcs1, _) => verifier.set_rsa_padding(Padding::PKCS1)?,
+        (SignatureAlgorithm::RsaPss, Some(digest)) => {
+            verifier.set_rsa_padding(Padding::PKCS1_PSS)?;
+            verifier.set_rsa_pss_saltlen(RsaPssSaltlen::custom(PSS_SALTLEN_AUTO))?;
+            verifier.set_rsa_mgf1_md(digest.message_digest()?)?;
+        }
+        _ => (),
+    }
+    Ok(verifier)
+}
+
+fn streaming_unsupported(algorithm: SignatureAlgorithm) -> anyhow::Error {
+    anyhow!(
+        "Algoritam {} ne podrzava potpisivanje u dijelovima",
+        algorithm
+    )
+}
+
+// RSA potpis u DigestInfo zapisuje OID sazetka, a BLAKE2 ga nema
+fn check_digest(algorithm: SignatureAlgorithm, digest: Option<HashAlgorithm>) -> Result<()> {
+    match (algorithm.key_algorithm(), digest) {
+        (
+            KeyAlgorithm::Rsa,
+            Some(digest @ (HashAlgorithm::Blake2b512 | HashAlgorithm::Blake2s256)),
+        ) => Err(anyhow!(
+            "Algoritam {} ne podrzava sazetak {}",
+            algorithm,
+            digest
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        encryption::MIN_RSA_KEY_LENGTH, file_manip::STREAM_BUFFER_SIZE, test_util::TempDir,
+    };
+    use openssl::rsa::Rsa;
+    use std::{fs, process::Command};
+
+    const OPENSSL_DATA: &[u8] = b"Operacijski sustavi 2 - digitalni potpis";
+
+    fn openssl_dgst(args: &[&str]) -> bool {
+        Command::new("openssl")
+            .arg("dgst")
+            .arg("-sha256")
+            .args(args)
+            .output()
+            .expect("openssl naredba nije dostupna")
+            .status
+            .success()
+    }
+
+    fn padding_args(padding: SignaturePadding) -> Vec<&'static str> {
+        match padding {
+            SignaturePadding::Pkcs1 => vec![],
+            SignaturePadding::Pss => vec![
+                "-sigopt",
+                "rsa_padding_mode:pss",
+                "-sigopt",
+                "rsa_pss_saltlen:digest",
+            ],
+        }
+    }
+
+    // RSA kljuc i podaci zapisani za naredbu openssl
+    fn openssl_files(dir: &TempDir) -> PKey<Private> {
+        let pkey = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        fs::write(
+            dir.path("privatni.pem"),
+            pkey.private_key_to_pem_pkcs8().unwrap(),
+        )
+        .unwrap();
+        fs::write(dir.path("javni.pem"), pkey.public_key_to_pem().unwrap()).unwrap();
+        fs::write(dir.path("datoteka"), OPENSSL_DATA).unwrap();
+        pkey
+    }
+
+    #[test]
+    #[ignore = "potreban je program openssl"]
+    fn openssl_cli_verifies_rsa_signatures() {
+        let dir = TempDir::new("potpis_openssl_provjera");
+        let pkey = openssl_files(&dir);
+        let (public_key_path, signature_path) = (dir.path("javni.pem"), dir.path("potpis"));
+        for padding in SignaturePadding::ALL {
+            let algorithm = SignatureAlgorithm::for_key(KeyAlgorithm::Rsa, padding);
+            fs::write(
+                &signature_path,
+                sign_with_algorithm(&pkey, algorithm, OPENSSL_DATA).unwrap(),
+            )
+            .unwrap();
+            let data_path = dir.path("datoteka");
+            let mut args = vec!["-verify", &public_key_path, "-signature", &signature_path];
+            args.extend(padding_args(padding));
+            args.push(&data_path);
+            assert!(
+                openssl_dgst(&args),
+                "openssl ne prihvaca potpis ({})",
+                padding
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "potreban je program openssl"]
+    fn openssl_cli_rsa_signatures_are_verified() {
+        let dir = TempDir::new("potpis_openssl_potpis");
+        let pkey = openssl_files(&dir);
+        let (private_key_path, signature_path) = (dir.path("privatni.pem"), dir.path("potpis"));
+        for padding in SignaturePadding::ALL {
+            let algorithm = SignatureAlgorithm::for_key(KeyAlgorithm::Rsa, padding);
+            let data_path = dir.path("datoteka");
+            let mut args = vec!["-sign", &private_key_path, "-out", &signature_path];
+            args.extend(padding_args(padding));
+            args.push(&data_path);
+            assert!(openssl_dgst(&args));
+            let signature = fs::read(&signature_path).unwrap();
+            assert!(
+                verify_with_algorithm(&pkey, algorithm, OPENSSL_DATA, &signature).unwrap(),
+                "{}",
+                padding
+            );
+            assert!(
+                !verify_with_algorithm(&pkey, algorithm, b"izmijenjeni podaci", &signature)
+                    .unwrap()
+            );
+        }
+    }
+
+    // Svaka vrsta kljuca, a RSA s oba nacina poravnanja
+    fn detached_signers() -> Vec<SigningKey> {
+        let rsa = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut signers: Vec<SigningKey> = SignaturePadding::ALL
+            .into_iter()
+            .map(|padding| {
+                let mut signer = SigningKey::new(rsa.clone());
+                signer.set_signature_padding(padding);
+                signer
+            })
+            .collect();
+        signers.extend(
+            [
+                generate_ec_key(Nid::X9_62_PRIME256V1).unwrap(),
+                generate_ec_key(Nid::SECP384R1).unwrap(),
+                PKey::generate_ed25519().unwrap(),
+            ]
+            .map(SigningKey::new),
+        );
+        signers
+    }
+
+    #[test]
+    fn detached_signatures_cover_only_file_contents() {
+        let dir = TempDir::new("potpis_samostalni");
+        let data_path = dir.path("datoteka");
+        fs::write(&data_path, OPENSSL_DATA).unwrap();
+        for signer in detached_signers() {
+            let algorithm = signer.algorithm().unwrap();
+            let signature = signer.sign_file_detached(&data_path).unwrap();
+            assert!(
+                verify_with_algorithm(&signer.pkey, algorithm, OPENSSL_DATA, &signature).unwrap(),
+                "{}",
+                algorithm
+            );
+            assert!(!verify_with_algorithm(
+                &signer.pkey,
+                algorithm,
+                b"izmijenjeni podaci",
+                &signature
+            )
+            .unwrap());
+        }
+    }
+
+    #[test]
+    #[ignore = "potreban je program openssl"]
+    fn openssl_cli_verifies_detached_file_signatures() {
+        let dir = TempDir::new("potpis_samostalni_openssl");
+        let (data_path, signature_path, public_key_path) = (
+            dir.path("datoteka"),
+            dir.path("potpis.sig"),
+            dir.path("javni.pem"),
+        );
+        fs::write(&data_path, OPENSSL_DATA).unwrap();
+        for signer in detached_signers() {
+            let algorithm = signer.algorithm().unwrap();
+            fs::write(&public_key_path, signer.pkey.public_key_to_pem().unwrap()).unwrap();
+            fs::write(
+                &signature_path,
+                signer.sign_file_detached(&data_path).unwrap(),
+            )
+            .unwrap();
+            let mut command = Command::new("openssl");
+            match algorithm.digest(None) {
+                Some(digest) => {
+                    command
+                        .arg("dgst")
+                        .arg(format!("-{}", digest.name().to_lowercase()))
+                        .args(["-verify", &public_key_path, "-signature", &signature_path]);
+                    if algorithm == SignatureAlgorithm::RsaPss {
+                        command.args(padding_args(SignaturePadding::Pss));
+                    }
+                    command.arg(&data_path)
+                }
+                None => command.args([
+                    "pkeyutl",
+                    "-verify",
+                    "-pubin",
+                    "-inkey",
+                    &public_key_path,
+                    "-rawin",
+                    "-in",
+                    &data_path,
+                    "-sigfile",
+                    &signature_path,
+                ]),
+            };
+            let output = command.output().expect("openssl naredba nije dostupna");
+            assert!(output.status.success(), "{}: {:?}", algorithm, output);
+        }
+    }
+
+    #[test]
+    fn short_rsa_keys_are_rejected() {
+        let pkey = PKey::from_rsa(Rsa::generate(1024).unwrap()).unwrap();
+        assert!(check_key_length(&pkey, 1024).is_ok());
+        let error = check_key_length(&pkey, 2048).unwrap_err();
+        assert!(error.to_string().contains("1024"));
+        assert!(check_key_length(&PKey::generate_ed25519().unwrap(), 4096).is_ok());
+    }
+
+    #[test]
+    fn rsa_signatures_round_trip_with_both_paddings() {
+        let pkey = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        for padding in SignaturePadding::ALL {
+            let algorithm = SignatureAlgorithm::for_key(KeyAlgorithm::Rsa, padding);
+            let signature = sign_with_algorithm(&pkey, algorithm, OPENSSL_DATA).unwrap();
+            assert!(verify_with_algorithm(&pkey, algorithm, OPENSSL_DATA, &signature).unwrap());
+            assert!(
+                !verify_with_algorithm(&pkey, algorithm, b"izmijenjeni podaci", &signature)
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn streamed_signatures_match_in_memory() {
+        let dir = TempDir::new("potpis_tok");
+        let data_path = dir.path("datoteka");
+        let data: Vec<u8> = (0..STREAM_BUFFER_SIZE * 4 + 7)
+            .map(|i| (i % 253) as u8)
+            .collect();
+        fs::write(&data_path, &data).unwrap();
+
+        let keys = [
+            PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap(),
+            generate_ec_key(Nid::X9_62_PRIME256V1).unwrap(),
+        ];
+        for pkey in keys {
+            let algorithm = SignatureAlgorithm::for_key(
+                KeyAlgorithm::of(&pkey).unwrap(),
+                SignaturePadding::Pss,
+            );
+            let digest = algorithm.default_digest();
+
+            let signature = sign_reader(
+                &pkey,
+                algorithm,
+                digest,
+                &mut File::open(&data_path).unwrap(),
+            )
+            .unwrap();
+            assert!(verify_with_algorithm(&pkey, algorithm, &data, &signature).unwrap());
+            assert!(verify_reader(
+                &pkey,
+                algorithm,
+                digest,
+                &mut File::open(&data_path).unwrap(),
+                &signature
+            )
+            .unwrap());
+
+            let in_memory = sign_with_algorithm(&pkey, algorithm, &data).unwrap();
+            assert!(verify_reader(
+                &pkey,
+                algorithm,
+                digest,
+                &mut File::open(&data_path).unwrap(),
+                &in_memory
+            )
+            .unwrap());
+            assert!(!verify_reader(&pkey, algorithm, digest, &mut &data[1..], &in_memory).unwrap());
+        }
+    }
+
+    // Potpisuje `signed` u zadanom nacinu i vraca provjeru potpisa za drugi sadrzaj datoteke
+    fn text_signature_checker(
+        name: &str,
+        mode: SignatureMode,
+        signed: &[u8],
+    ) -> impl Fn(&[u8]) -> bool {
+        let dir = TempDir::new(name);
+        let pkey = PKey::generate_ed25519().unwrap();
+        fs::write(dir.path("javni.pem"), pkey.public_key_to_pem().unwrap()).unwrap();
+        let mut signer = SigningKey::new(pkey);
+        signer.set_mode(mode);
+        fs::write(dir.path("dokument.txt"), signed).unwrap();
+        let signature = signer.sign_file(&dir.path("dokument.txt")).unwrap();
+        fs::write(dir.path("potpis"), signature).unwrap();
+        // Nacin se cita iz potpisa, a ne iz postavki provjere
+        let verifier =
+            VerifyKey::from_file(Some(&dir.path("javni.pem")), MIN_RSA_KEY_LENGTH).unwrap();
+        move |contents: &[u8]| {
+            fs::write(dir.path("dokument.txt"), contents).unwrap();
+            verifier
+                .verify_file_signature(&dir.path("dokument.txt"), &dir.path("potpis"))
+                .is_valid()
+        }
+    }
+
+    #[test]
+    fn text_signatures_ignore_line_endings() {
+        let verify = text_signature_checker(
+            "potpis_tekst",
+            SignatureMode::Text,
+            b"prvi red\r\ndrugi red  \r\n",
+        );
+        assert!(verify(b"prvi red\ndrugi red  \n"));
+        assert!(!verify(b"prvi red\ndrugi red\n"));
+    }
+
+    #[test]
+    fn trimmed_text_signatures_ignore_trailing_whitespace() {
+        let verify = text_signature_checker(
+            "potpis_tekst_bez_razmaka",
+            SignatureMode::TextTrimmed,
+            b"prvi red\r\ndrugi red  \r\n",
+        );
+        assert!(verify(b"prvi red\ndrugi red\n"));
+        assert!(!verify(b"prvi  red\ndrugi red\n"));
+    }
+
+    #[test]
+    fn binary_signatures_keep_line_endings() {
+        let verify =
+            text_signature_checker("potpis_binarni", SignatureMode::Binary, b"prvi red\r\n");
+        assert!(verify(b"prvi red\r\n"));
+        assert!(!verify(b"prvi red\n"));
+    }
+
+    #[test]
+    fn signature_metadata_is_signed() {
+        let dir = TempDir::new("potpis_podaci");
+        let (data_path, signature_path) = (dir.path("dokument.txt"), dir.path("potpis"));
+        let pkey = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        fs::write(dir.path("javni.pem"), pkey.public_key_to_pem().unwrap()).unwrap();
+        let verifier =
+            VerifyKey::from_file(Some(&dir.path("javni.pem")), MIN_RSA_KEY_LENGTH).unwrap();
+        fs::write(&data_path, b"prvi red\r\n").unwrap();
+        let mut signature = SigningKey::new(pkey).sign_file(&data_path).unwrap();
+        let created_offset = SIGNATURE_MAGIC.len() + 4 + 4 + 4 + 32;
+        signature[created_offset + 7] ^= 1;
+        fs::write(&signature_path, signature).unwrap();
+        assert!(!verifier
+            .verify_file_signature(&data_path, &signature_path)
+            .is_valid());
+    }
+
+    #[test]
+    fn attached_signatures_extract_only_valid_messages() {
+        let dir = TempDir::new("potpis_poruka");
+        let (data_path, attached_path, extracted_path, public_key_path) = (
+            dir.path("datoteka"),
+            dir.path("poruka"),
+            dir.path("izdvojeno"),
+            dir.path("javni.pem"),
+        );
+
+        let data: Vec<u8> = (0..STREAM_BUFFER_SIZE + 5)
+            .map(|i| (i % 249) as u8)
+            .collect();
+        fs::write(&data_path, &data).unwrap();
+        let keys = [
+            generate_ec_key(Nid::X9_62_PRIME256V1).unwrap(),
+            PKey::generate_ed25519().unwrap(),
+        ];
+        for pkey in keys {
+            fs::write(&public_key_path, pkey.public_key_to_pem().unwrap()).unwrap();
+            let signer = SigningKey::new(pkey);
+            let verifier =
+                VerifyKey::from_file(Some(&public_key_path), MIN_RSA_KEY_LENGTH).unwrap();
+
+            for armored in [false, true] {
+                signer
+                    .sign_attached_file(&data_path, &attached_path, armored)
+                    .unwrap();
+                let attached = fs::read(&attached_path).unwrap();
+                assert_eq!(attached.starts_with(ARMOR_BEGIN.as_bytes()), armored);
+                let verification = verifier.verify_attached_file(&attached_path, &extracted_path);
+                assert!(verification.is_valid());
+                let metadata = verification.info.unwrap().metadata.unwrap();
+                assert_eq!(metadata.filename, "datoteka");
+                assert_eq!(metadata.file_size, data.len() as u64);
+                assert_eq!(
+                    metadata.signer_fingerprint,
+                    key_fingerprint(verifier.public_key().unwrap()).unwrap()
+                );
+                assert_eq!(fs::read(&extracted_path).unwrap(), data);
+                fs::remove_file(&extracted_path).unwrap();
+
+                // Izmijenjena poruka se ne izdvaja
+                let mut tampered = if armored {
+                    dearmor(std::str::from_utf8(&attached).unwrap()).unwrap()
+                } else {
+                    attached
+                };
+                *tampered.last_mut().unwrap() ^= 1;
+                if armored {
+                    tampered = armor(&tampered).into_bytes();
+                }
+                fs::write(&attached_path, tampered).unwrap();
+                assert!(!verifier
+                    .verify_attached_file(&attached_path, &extracted_path)
+                    .is_valid());
+                assert!(fs::metadata(&extracted_path).is_err());
+            }
+        }
+    }
+
+    // Za svaku vrstu kljuca i RSA poravnanje potpisuje datoteku i provjerava ishod `check`
+    fn check_outcomes(name: &str, check: impl Fn(&TempDir, &[u8], &VerifyKey, &VerifyKey)) {
+        let dir = TempDir::new(name);
+        let keys = [
+            (
+                PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap(),
+                PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap(),
+            ),
+            (
+                generate_ec_key(Nid::X9_62_PRIME256V1).unwrap(),
+                generate_ec_key(Nid::X9_62_PRIME256V1).unwrap(),
+            ),
+            (
+                PKey::generate_ed25519().unwrap(),
+                PKey::generate_ed25519().unwrap(),
+            ),
+        ];
+        for (pkey, other) in keys {
+            let (public_key_path, other_key_path) = (dir.path("javni.pem"), dir.path("drugi.pem"));
+            fs::write(&public_key_path, pkey.public_key_to_pem().unwrap()).unwrap();
+            fs::write(&other_key_path, other.public_key_to_pem().unwrap()).unwrap();
+            let verifier =
+                VerifyKey::from_file(Some(&public_key_path), MIN_RSA_KEY_LENGTH).unwrap();
+            let other_verifier =
+                VerifyKey::from_file(Some(&other_key_path), MIN_RSA_KEY_LENGTH).unwrap();
+            let mut signer = SigningKey::new(pkey);
+            for padding in SignaturePadding::ALL {
+                signer.set_signature_padding(padding);
+                fs::write(dir.path("datoteka"), b"izvorna datoteka").unwrap();
+                let signature = signer.sign_file(&dir.path("datoteka")).unwrap();
+                fs::write(dir.path("potpis"), &signature).unwrap();
+                check(&dir, &signature, &verifier, &other_verifier);
+            }
+        }
+    }
+
+    fn outcome(dir: &TempDir, verifier: &VerifyKey) -> VerificationOutcome {
+        verifier
+            .verify_file_signature(&dir.path("datoteka"), &dir.path("potpis"))
+            .outcome
+    }
+
+    #[test]
+    fn verification_reports_wrong_key() {
+        check_outcomes("potpis_kljuc", |dir, _, verifier, other_verifier| {
+            assert!(matches!(outcome(dir, verifier), VerificationOutcome::Valid));
+            assert!(matches!(
+                outcome(dir, other_verifier),
+                VerificationOutcome::WrongKey(_)
+            ));
+        });
+    }
+
+    #[test]
+    fn verification_reports_changed_file() {
+        check_outcomes("potpis_izmjena", |dir, _, verifier, _| {
+            fs::write(dir.path("datoteka"), b"izmijenjena datoteka").unwrap();
+            assert!(matches!(
+                outcome(dir, verifier),
+                VerificationOutcome::FileChanged
+            ));
+        });
+    }
+
+    #[test]
+    fn verification_reports_damaged_signature() {
+        check_outcomes("potpis_osteceni", |dir, signature, verifier, _| {
+            fs::write(dir.path("potpis"), &signature[..signature.len() - 3]).unwrap();
+            assert!(matches!(
+                outcome(dir, verifier),
+                VerificationOutcome::SignatureDamaged(_)
+            ));
+            fs::write(dir.path("potpis"), &signature[..5]).unwrap();
+            assert!(matches!(
+                outcome(dir, verifier),
+                VerificationOutcome::SignatureDamaged(_)
+            ));
+        });
+    }
+
+    #[test]
+    fn verification_reports_missing_signature() {
+        check_outcomes("potpis_nedostaje", |dir, _, verifier, _| {
+            fs::remove_file(dir.path("potpis")).unwrap();
+            assert!(matches!(outcome(dir, verifier), VerificationOutcome::Io(_)));
+        });
+    }
+
+    #[test]
+    fn raw_rsa_signatures_without_header_are_verified() {
+        let dir = TempDir::new("potpis_stari");
+        let (data_path, signature_path, public_key_path) = (
+            dir.path("datoteka"),
+            dir.path("potpis"),
+            dir.path("javni.pem"),
+        );
+
+        let rsa = Rsa::generate(2048).unwrap();
+        fs::write(&public_key_path, rsa.public_key_to_pem().unwrap()).unwrap();
+        let verifier = VerifyKey::from_file(Some(&public_key_path), MIN_RSA_KEY_LENGTH).unwrap();
+        let hash = ShaHash::hash(b"stari potpis").unwrap();
+        let mut signature = vec![0; rsa.size() as usize];
+        rsa.private_encrypt(&hash, &mut signature, Padding::PKCS1)
+            .unwrap();
+        fs::write(&signature_path, &signature).unwrap();
+        let outcome = |contents: &[u8]| {
+            fs::write(&data_path, contents).unwrap();
+            verifier
+                .verify_file_signature(&data_path, &signature_path)
+                .outcome
+        };
+
+        // Zavrsni prijelom retka se zanemaruje kao u staroj verziji
+        assert!(matches!(
+            outcome(b"stari potpis"),
+            VerificationOutcome::Valid
+        ));
+        assert!(matches!(
+            outcome(b"stari potpis\r\n"),
+            VerificationOutcome::Valid
+        ));
+        assert!(matches!(
+            outcome(b"novi potpis"),
+            VerificationOutcome::FileChanged
+        ));
+    }
+}
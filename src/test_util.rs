@@ -0,0 +1,23 @@
+use std::{fs, path::PathBuf, process};
+
+// Privremeni direktorij testa, brise se kad test zavrsi (i kad ne uspije)
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    // Naziv mora biti jedinstven jer se testovi izvode paralelno
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("os2_{}_{}", name, process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub fn path(&self, name: &str) -> String {
+        self.0.join(name).to_string_lossy().to_string()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,579 @@
+use std::{
+    cmp::Ordering,
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use openssl::{
+    asn1::Asn1Time,
+    sha::sha256,
+    stack::Stack,
+    x509::{store::X509StoreBuilder, X509Ref, X509StoreContext, X509VerifyResult, X509},
+};
+use openssl_sys as ffi;
+
+use crate::{
+    certificate::{allows_signing, is_certificate_authority, load_certificate, name_to_string},
+    crl::{load_crl, RevokedCertificate, X509Crl, X509CrlRef},
+    file_manip::{read_file_to_buffer, write_file},
+    fingerprint::{key_fingerprint, short_key_id},
+};
+
+pub const TRUSTED_ROOTS_DIRECTORY: &str = "pouzdani_korijenski_certifikati";
+// Liste opozvanih certifikata su u poddirektoriju, po jedna za svakog izdavatelja
+const CRL_DIRECTORY: &str = "liste_opozvanih_certifikata";
+
+pub struct TrustStore {
+    directory: String,
+    roots: Vec<X509>,
+    crls: Vec<X509Crl>,
+}
+
+// Certifikat iz lanca potpisnika je opozvan u listi opozvanih certifikata izdavatelja
+#[derive(Debug)]
+pub struct CertificateRevoked {
+    pub subject: String,
+    pub revoked: RevokedCertificate,
+}
+
+impl fmt::Display for CertificateRevoked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "certifikat {} je {}", self.subject, self.revoked)
+    }
+}
+
+impl Error for CertificateRevoked {}
+
+impl TrustStore {
+    // Svaka datoteka u direktoriju je jedan pouzdani korijenski certifikat
+    pub fn open(directory: Option<&str>) -> Result<Self> {
+        let directory = directory.unwrap_or(TRUSTED_ROOTS_DIRECTORY).to_string();
+        let roots = files(Path::new(&directory))?
+            .iter()
+            .map(|path| {
+                load_certificate(&read_file_to_buffer(&path.to_string_lossy())?)
+                    .map_err(|_| anyhow!("Datoteka {} nije certifikat", path.display()))
+            })
+            .collect::<Result<_>>()?;
+        let crls = files(&Path::new(&directory).join(CRL_DIRECTORY))?
+            .iter()
+            .map(|path| load_crl(&read_file_to_buffer(&path.to_string_lossy())?))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            directory,
+            roots,
+            crls,
+        })
+    }
+
+    pub fn roots(&self) -> &[X509] {
+        &self.roots
+    }
+
+    pub fn crls(&self) -> &[X509Crl] {
+        &self.crls
+    }
+
+    // Nova lista istog izdavatelja zamjenjuje staru, osim ako je stara novija
+    pub fn add_crl(&mut self, crl: &X509CrlRef) -> Result<String> {
+        for existing in &self.crls {
+            if existing.issuer_name().try_cmp(crl.issuer_name())? != Ordering::Equal {
+                continue;
+            }
+            if let (Some(existing), Some(new)) = (existing.crl_number(), crl.crl_number()) {
+                if new < existing {
+                    return Err(anyhow!(
+                        "Vec je dodana novija lista opozvanih certifikata istog izdavatelja (broj {})",
+                        existing
+                    ));
+                }
+            }
+        }
+        let directory = Path::new(&self.directory).join(CRL_DIRECTORY);
+        fs::create_dir_all(&directory)?;
+        let issuer_id = short_key_id(&sha256(&crl.issuer_name().to_der()?));
+        let path = directory
+            .join(format!("{}.pem", issuer_id))
+            .to_string_lossy()
+            .to_string();
+        write_file(&path, &crl.to_pem()?, false)?;
+        self.crls = Self::open(Some(&self.directory))?.crls;
+        Ok(path)
+    }
+
+    // Pouzdan moze biti samo samopotpisani certifikat certifikacijskog tijela,
+    // a sprema se pod ID-em javnog kljuca
+    pub fn add_root(&mut self, certificate: &X509Ref) -> Result<String> {
+        let public_key = certificate.public_key()?;
+        let self_issued = certificate
+            .issuer_name()
+            .try_cmp(certificate.subject_name())?
+            == Ordering::Equal;
+        if !self_issued || !certificate.verify(&public_key)? {
+            return Err(anyhow!(
+                "Certifikat nije samopotpisani korijenski certifikat"
+            ));
+        }
+        if !is_certificate_authority(certificate) {
+            return Err(anyhow!("Certifikat ne pripada certifikacijskom tijelu"));
+        }
+        fs::create_dir_all(&self.directory)?;
+        let filename = format!("{}.pem", short_key_id(&key_fingerprint(&public_key)?));
+        let path = Path::new(&self.directory)
+            .join(filename)
+            .to_string_lossy()
+            .to_string();
+        write_file(&path, &certificate.to_pem()?, false)?;
+        self.roots = Self::open(Some(&self.directory))?.roots;
+        Ok(path)
+    }
+
+    // OpenSSL provjerava potpise, razdoblja valjanosti, osnovna ogranicenja i namjenu kljuca
+    // certifikacijskih tijela u lancu, a namjena kljuca potpisnika se provjerava posebno.
+    // Lanac pocinje certifikatom potpisnika, a vraca se opis potpisnika.
+    pub fn verify_chain(&self, chain: &[X509]) -> Result<String> {
+        let (signer, intermediates) = chain
+            .split_first()
+            .ok_or_else(|| anyhow!("potpis ne sadrzi lanac certifikata potpisnika"))?;
+        if self.roots.is_empty() {
+            return Err(anyhow!("nema pouzdanih korijenskih certifikata"));
+        }
+        let mut builder = X509StoreBuilder::new()?;
+        for root in &self.roots {
+            builder.add_cert(root.clone())?;
+        }
+        let store = builder.build();
+        let mut untrusted = Stack::new()?;
+        for certificate in intermediates {
+            untrusted.push(certificate.clone())?;
+        }
+
+        let mut context = X509StoreContext::new()?;
+        let (error, verified) = context.init(&store, signer, &untrusted, |context| {
+            let error = match context.verify_cert()? {
+                true => None,
+                false => Some((
+                    context.error(),
+                    context
+                        .current_cert()
+                        .map(|certificate| name_to_string(certificate.subject_name())),
+                )),
+            };
+            let verified: Vec<X509> = context
+                .chain()
+                .map(|chain| {
+                    chain
+                        .iter()
+                        .map(|certificate| certificate.to_owned())
+                        .collect()
+                })
+                .unwrap_or_default();
+            Ok((error, verified))
+        })?;
+        if let Some((error, subject)) = error {
+            return Err(match subject {
+                Some(subject) => anyhow!("{} (certifikat {})", verify_error(error), subject),
+                None => anyhow!("{}", verify_error(error)),
+            });
+        }
+        // Svaki certifikat osim korijenskog se trazi u listi opozvanih certifikata izdavatelja
+        for pair in verified.windows(2) {
+            if let Some(revoked) = self.revocation(&pair[0], &pair[1])? {
+                return Err(CertificateRevoked {
+                    subject: name_to_string(pair[0].subject_name()),
+                    revoked,
+                }
+                .into());
+            }
+        }
+        if !allows_signing(signer) {
+            return Err(anyhow!(
+                "namjena kljuca certifikata potpisnika ne dopusta potpisivanje"
+            ));
+        }
+        Ok(format!(
+            "{} (izdavatelj: {}, pouzdani korijen: {})",
+            name_to_string(signer.subject_name()),
+            name_to_string(signer.issuer_name()),
+            verified
+                .last()
+                .map(|root| name_to_string(root.subject_name()))
+                .unwrap_or_default()
+        ))
+    }
+
+    // Koristi se samo lista koju je potpisao izdavatelj certifikata
+    fn revocation(
+        &self,
+        certificate: &X509Ref,
+        issuer: &X509Ref,
+    ) -> Result<Option<RevokedCertificate>> {
+        let public_key = issuer.public_key()?;
+        let serial_number = certificate.serial_number().to_bn()?;
+        let now = Asn1Time::days_from_now(0)?;
+        for crl in &self.crls {
+            if crl.issuer_name().try_cmp(issuer.subject_name())? != Ordering::Equal {
+                continue;
+            }
+            // Lista koja se ne moze provjeriti ne smije tiho propustiti opozvani certifikat
+            let issuer_name = name_to_string(issuer.subject_name());
+            if !crl.verify(&public_key) {
+                return Err(anyhow!(
+                    "lista opozvanih certifikata izdavatelja {} nije ispravno potpisana",
+                    issuer_name
+                ));
+            }
+            match crl.next_update() {
+                Some(next_update) if next_update > now => {}
+                next_update => {
+                    return Err(anyhow!(
+                    "lista opozvanih certifikata izdavatelja {} je zastarjela (sljedeca lista: {})",
+                    issuer_name,
+                    next_update.map_or("nije navedena".to_string(), |time| time.to_string())
+                ))
+                }
+            }
+            if let Some(revoked) = crl.find(&serial_number)? {
+                return Ok(Some(revoked));
+            }
+        }
+        Ok(None)
+    }
+}
+
+// Datoteke u direktoriju abecednim redom, direktorij ne mora postojati
+fn files(directory: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .map(|entry| Ok(entry?.path()))
+            .collect::<Result<Vec<_>>>()?,
+        Err(_) => Vec::new(),
+    };
+    paths.retain(|path| path.is_file());
+    paths.sort();
+    Ok(paths)
+}
+
+fn verify_error(error: X509VerifyResult) -> String {
+    match error.as_raw() {
+        ffi::X509_V_ERR_CERT_HAS_EXPIRED => "certifikat je istekao".to_string(),
+        ffi::X509_V_ERR_CERT_NOT_YET_VALID => "certifikat jos ne vrijedi".to_string(),
+        ffi::X509_V_ERR_UNABLE_TO_GET_ISSUER_CERT_LOCALLY
+        | ffi::X509_V_ERR_UNABLE_TO_GET_ISSUER_CERT => {
+            "lanac ne zavrsava pouzdanim korijenskim certifikatom".to_string()
+        }
+        ffi::X509_V_ERR_DEPTH_ZERO_SELF_SIGNED_CERT | ffi::X509_V_ERR_SELF_SIGNED_CERT_IN_CHAIN => {
+            "samopotpisani certifikat nije medu pouzdanim korijenskim certifikatima".to_string()
+        }
+        ffi::X509_V_ERR_INVALID_CA => "izdavatelj nije certifikacijsko tijelo".to_string(),
+        ffi::X509_V_ERR_PATH_LENGTH_EXCEEDED => {
+            "prekoracena dopustena duljina lanca certifikata".to_string()
+        }
+        ffi::X509_V_ERR_KEYUSAGE_NO_CERTSIGN => {
+            "namjena kljuca izdavatelja ne dopusta potpisivanje certifikata".to_string()
+        }
+        ffi::X509_V_ERR_CERT_SIGNATURE_FAILURE => "potpis certifikata nije ispravan".to_string(),
+        _ => error.error_string().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        certificate::{
+            create_certificate_request, create_self_signed_certificate, issue_certificate,
+            random_serial_number, subject_name, CertificateParams, KeyUsage,
+        },
+        certificate_authority::CertificateAuthority,
+        crl::RevocationReason,
+        encryption::RsaKeyParams,
+        signing::{generate_key_pair, KeyAlgorithm, SigningKey, VerificationOutcome, VerifyKey},
+        test_util::TempDir,
+    };
+    use openssl::{
+        bn::BigNum,
+        pkey::{PKey, Private},
+    };
+
+    // Korijensko i podredeno tijelo, drugo korijensko tijelo i kljuc korisnika
+    struct Pki {
+        dir: TempDir,
+        root: CertificateAuthority,
+        other_root: CertificateAuthority,
+        intermediate: CertificateAuthority,
+        user_key: PKey<Private>,
+    }
+
+    impl Pki {
+        fn new(name: &str) -> Self {
+            let dir = TempDir::new(name);
+            let ca_params = |subject: &str, validity_days: u32| CertificateParams {
+                subject: subject.to_string(),
+                validity_days,
+                ..CertificateParams::default()
+            };
+            let create_root = |directory: &str, subject: &str| {
+                CertificateAuthority::create_root(
+                    &dir.path(directory),
+                    &ca_params(subject, 3650),
+                    KeyAlgorithm::EcdsaP256,
+                    RsaKeyParams::default(),
+                    None,
+                )
+                .unwrap()
+            };
+            let root = create_root("root", "CN=OS2 Root CA");
+            let other_root = create_root("other_root", "CN=Drugo CA");
+            let intermediate = root
+                .create_intermediate(
+                    &dir.path("intermediate"),
+                    &ca_params("CN=OS2 Intermediate CA", 730),
+                    KeyAlgorithm::Ed25519,
+                    RsaKeyParams::default(),
+                    None,
+                    None,
+                )
+                .unwrap();
+            let user_key =
+                generate_key_pair(KeyAlgorithm::Ed25519, RsaKeyParams::default()).unwrap();
+            Self {
+                dir,
+                root,
+                other_root,
+                intermediate,
+                user_key,
+            }
+        }
+
+        fn issue(&self, key_usage: Vec<KeyUsage>) -> Vec<X509> {
+            let params = CertificateParams {
+                subject: "CN=Ivan Horvat".to_string(),
+                key_usage,
+                ..CertificateParams::default()
+            };
+            let request = create_certificate_request(&self.user_key, &params).unwrap();
+            let mut chain = vec![self
+                .intermediate
+                .sign_request(&request, &params, None)
+                .unwrap()];
+            chain.extend(self.intermediate.chain().unwrap());
+            chain
+        }
+
+        fn trust_store(&self, name: &str, root: &CertificateAuthority) -> TrustStore {
+            let mut trust_store = TrustStore::open(Some(&self.dir.path(name))).unwrap();
+            trust_store.add_root(root.certificate()).unwrap();
+            trust_store
+        }
+
+        // Potpisuje dokument kljucem korisnika i vraca putanje dokumenta i potpisa
+        fn sign(&self, chain: Option<Vec<X509>>) -> (String, String) {
+            let (data_path, signature_path) =
+                (self.dir.path("dokument.txt"), self.dir.path("potpis"));
+            fs::write(&data_path, b"potpisani dokument").unwrap();
+            let mut signing_key = SigningKey::new(self.user_key.clone());
+            if let Some(chain) = chain {
+                signing_key.set_certificate_chain(chain).unwrap();
+            }
+            fs::write(&signature_path, signing_key.sign_file(&data_path).unwrap()).unwrap();
+            (data_path, signature_path)
+        }
+    }
+
+    #[test]
+    fn only_self_signed_ca_certificates_are_trusted_roots() {
+        let pki = Pki::new("pouzdani_korijeni");
+        let chain = pki.issue(vec![KeyUsage::DigitalSignature]);
+        assert_eq!(chain.len(), 3);
+        let mut trust_store = TrustStore::open(Some(&pki.dir.path("pouzdani"))).unwrap();
+        assert!(trust_store.roots().is_empty());
+        assert!(trust_store.add_root(&chain[0]).is_err());
+        assert!(trust_store
+            .add_root(pki.intermediate.certificate())
+            .is_err());
+        trust_store.add_root(pki.root.certificate()).unwrap();
+        trust_store.add_root(pki.root.certificate()).unwrap();
+        assert_eq!(trust_store.roots().len(), 1);
+        assert_eq!(
+            TrustStore::open(Some(&pki.dir.path("pouzdani")))
+                .unwrap()
+                .roots()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn chains_are_verified_up_to_a_trusted_root() {
+        let pki = Pki::new("pouzdani_lanac");
+        let trust_store = pki.trust_store("pouzdani", &pki.root);
+        let chain = pki.issue(vec![KeyUsage::DigitalSignature]);
+        let signer = trust_store.verify_chain(&chain).unwrap();
+        assert!(signer.starts_with("CN=Ivan Horvat (izdavatelj: CN=OS2 Intermediate CA"));
+        assert!(signer.ends_with("pouzdani korijen: CN=OS2 Root CA)"));
+        assert!(trust_store.verify_chain(&chain[..1]).is_err());
+
+        let encryption_only = pki.issue(vec![KeyUsage::KeyEncipherment]);
+        assert!(trust_store
+            .verify_chain(&encryption_only)
+            .unwrap_err()
+            .to_string()
+            .contains("namjena kljuca"));
+
+        // Certifikat koji nije certifikacijsko tijelo ne moze izdavati certifikate
+        let other_key = generate_key_pair(KeyAlgorithm::Ed25519, RsaKeyParams::default()).unwrap();
+        let forged = issue_certificate(
+            &subject_name("CN=Lazni potpisnik").unwrap(),
+            &other_key,
+            &random_serial_number().unwrap(),
+            &CertificateParams::default(),
+            Some(&chain[0]),
+            &pki.user_key,
+        )
+        .unwrap();
+        let mut forged_chain = vec![forged];
+        forged_chain.extend(chain.iter().cloned());
+        assert!(trust_store.verify_chain(&forged_chain).is_err());
+    }
+
+    #[test]
+    fn certificate_chain_is_signed_with_the_file() {
+        let pki = Pki::new("pouzdani_potpis");
+        let chain = pki.issue(vec![KeyUsage::DigitalSignature]);
+        let other_key = generate_key_pair(KeyAlgorithm::Ed25519, RsaKeyParams::default()).unwrap();
+        assert!(SigningKey::new(other_key)
+            .set_certificate_chain(chain.clone())
+            .is_err());
+
+        let (data_path, signature_path) = pki.sign(Some(chain));
+        let verifier = VerifyKey::from_trust_store(pki.trust_store("pouzdani", &pki.root));
+        let verification = verifier.verify_file_signature(&data_path, &signature_path);
+        assert!(verification.is_valid());
+        assert!(verification
+            .signer
+            .unwrap()
+            .starts_with("CN=Ivan Horvat (izdavatelj: CN=OS2 Intermediate CA"));
+        assert_eq!(verification.info.unwrap().certificates.len(), 3);
+
+        fs::write(&data_path, b"izmijenjeni dokument").unwrap();
+        let verification = verifier.verify_file_signature(&data_path, &signature_path);
+        assert!(matches!(
+            verification.outcome,
+            VerificationOutcome::FileChanged
+        ));
+        assert!(verification.signer.is_none());
+    }
+
+    #[test]
+    fn signatures_need_a_chain_to_a_trusted_root() {
+        let pki = Pki::new("pouzdani_nepouzdani");
+        let (data_path, signature_path) =
+            pki.sign(Some(pki.issue(vec![KeyUsage::DigitalSignature])));
+        let verification = VerifyKey::from_trust_store(pki.trust_store("drugi", &pki.other_root))
+            .verify_file_signature(&data_path, &signature_path);
+        assert!(matches!(
+            verification.outcome,
+            VerificationOutcome::UntrustedCertificate(_)
+        ));
+        assert!(verification.signer.is_none());
+
+        // Potpis bez lanca se ne moze provjeriti samo pouzdanim korijenima
+        let (data_path, signature_path) = pki.sign(None);
+        assert!(matches!(
+            VerifyKey::from_trust_store(pki.trust_store("pouzdani", &pki.root))
+                .verify_file_signature(&data_path, &signature_path)
+                .outcome,
+            VerificationOutcome::UntrustedCertificate(_)
+        ));
+    }
+
+    #[test]
+    fn revocation_applies_once_the_crl_is_added() {
+        let pki = Pki::new("pouzdani_opoziv");
+        let chain = pki.issue(vec![KeyUsage::DigitalSignature]);
+        let serial_number = chain[0].serial_number().to_bn().unwrap();
+        let (data_path, signature_path) = pki.sign(Some(chain));
+        pki.intermediate
+            .revoke(
+                &serial_number.to_hex_str().unwrap(),
+                RevocationReason::KeyCompromise,
+            )
+            .unwrap();
+        let crl = pki.intermediate.create_crl(None).unwrap();
+        assert!(
+            VerifyKey::from_trust_store(pki.trust_store("pouzdani", &pki.root))
+                .verify_file_signature(&data_path, &signature_path)
+                .is_valid()
+        );
+
+        let mut trust_store = pki.trust_store("pouzdani", &pki.root);
+        trust_store
+            .add_crl(&pki.other_root.create_crl(None).unwrap())
+            .unwrap();
+        trust_store.add_crl(&crl).unwrap();
+        assert_eq!(trust_store.crls().len(), 2);
+        let verification = VerifyKey::from_trust_store(trust_store)
+            .verify_file_signature(&data_path, &signature_path);
+        match verification.outcome {
+            VerificationOutcome::RevokedCertificate(reason) => {
+                assert!(reason.starts_with("certifikat CN=Ivan Horvat je opozvan"));
+                assert!(reason.ends_with("razlog: kompromitiran kljuc"));
+            }
+            outcome => panic!("{}", outcome),
+        }
+    }
+
+    #[test]
+    fn stale_or_badly_signed_crls_are_rejected() {
+        let dir = TempDir::new("pouzdani_crl");
+        let root_key = generate_key_pair(KeyAlgorithm::Ed25519, RsaKeyParams::default()).unwrap();
+        let root = create_self_signed_certificate(
+            &root_key,
+            &CertificateParams {
+                subject: "CN=OS2 Root CA".to_string(),
+                certificate_authority: true,
+                ..CertificateParams::default()
+            },
+        )
+        .unwrap();
+        let user_key = generate_key_pair(KeyAlgorithm::Ed25519, RsaKeyParams::default()).unwrap();
+        let user = issue_certificate(
+            &subject_name("CN=Ivan Horvat").unwrap(),
+            &user_key,
+            &random_serial_number().unwrap(),
+            &CertificateParams::default(),
+            Some(&root),
+            &root_key,
+        )
+        .unwrap();
+        let chain = vec![user, root.clone()];
+
+        let mut trust_store = TrustStore::open(Some(&dir.path("pouzdani"))).unwrap();
+        trust_store.add_root(&root).unwrap();
+        let crl = |number: u32, key: &PKey<Private>, next_update_days: u32| {
+            let number = BigNum::from_u32(number).unwrap();
+            X509Crl::create(&root, key, &[], &number, next_update_days).unwrap()
+        };
+        trust_store.add_crl(&crl(1, &root_key, 30)).unwrap();
+        assert!(trust_store.verify_chain(&chain).is_ok());
+
+        let other_key = generate_key_pair(KeyAlgorithm::Ed25519, RsaKeyParams::default()).unwrap();
+        trust_store.add_crl(&crl(2, &other_key, 30)).unwrap();
+        assert!(trust_store
+            .verify_chain(&chain)
+            .unwrap_err()
+            .to_string()
+            .contains("nije ispravno potpisana"));
+
+        // Lista vrijedi samo do najave sljedece liste
+        trust_store.add_crl(&crl(3, &root_key, 0)).unwrap();
+        assert!(trust_store
+            .verify_chain(&chain)
+            .unwrap_err()
+            .to_string()
+            .contains("je zastarjela"));
+    }
+}